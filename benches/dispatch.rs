@@ -0,0 +1,57 @@
+use std::time::{Duration, Instant};
+
+use ivm::{BufferIo, VM};
+
+const COUNTER_LOOP: &str = r#"
+PUSH INT 0
+STORE i
+
+LABEL loop
+    LOAD i
+    PUSH INT 1
+    ADD
+    DUP
+    STORE i
+    PUSH INT 1000000
+    CMP LESS_THAN
+    JMP_IF_TRUE loop
+"#;
+
+const CALL_LOOP: &str = r#"
+PUSH INT 0
+JMP loop
+
+LABEL increment
+    PUSH INT 1
+    ADD
+    RET
+
+LABEL loop
+    CALL increment
+    DUP
+    PUSH INT 1000000
+    CMP LESS_THAN
+    JMP_IF_TRUE loop
+"#;
+
+fn bench(name: &str, source: &str) {
+    let bytecode = ivm::assemble(source).expect("benchmark program should assemble");
+    let program = VM::load(&bytecode).expect("benchmark program should load");
+    let runs = 5;
+    let mut best = Duration::MAX;
+
+    for _ in 0..runs {
+        let mut vm = VM::builder(program.clone()).io(BufferIo::default()).build();
+
+        let start = Instant::now();
+        vm.run().expect("benchmark program should run");
+        best = best.min(start.elapsed());
+    }
+
+    println!("{:<16} best of {}: {:?}", name, runs, best);
+}
+
+fn main() {
+    bench("counter_loop", COUNTER_LOOP);
+    bench("call_loop", CALL_LOOP);
+}
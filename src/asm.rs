@@ -0,0 +1,468 @@
+use std::fmt;
+
+use crate::{
+    bigint::BigInt,
+    instruction::{Instruction, Target},
+    opcode::*,
+    parse::parse_integer,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+#[derive(Debug)]
+enum TokenKind {
+    Word(String),
+    Str(String),
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    column: usize,
+}
+
+fn tokenize(line: &str, line_number: usize) -> Result<Vec<Token>, AsmError> {
+    let error = |column: usize, message: &str| AsmError {
+        line: line_number,
+        column,
+        message: message.to_string(),
+    };
+
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().enumerate().peekable();
+
+    while let Some(&(index, c)) = chars.peek() {
+        let column = index + 1;
+
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == ';' {
+            break;
+        } else if c == '"' {
+            chars.next();
+
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, '"')) => break,
+                    Some((escape_index, '\\')) => {
+                        let escaped = match chars.next() {
+                            Some((_, 'n')) => '\n',
+                            Some((_, 't')) => '\t',
+                            Some((_, 'r')) => '\r',
+                            Some((_, '0')) => '\0',
+                            Some((_, '\\')) => '\\',
+                            Some((_, '"')) => '"',
+                            Some((_, 'u')) => {
+                                let mut digits = String::new();
+                                if !matches!(chars.next(), Some((_, '{'))) {
+                                    return Err(error(escape_index + 1, "expected '{' after \\u"));
+                                }
+                                loop {
+                                    match chars.next() {
+                                        Some((_, '}')) => break,
+                                        Some((_, digit)) => digits.push(digit),
+                                        None => {
+                                            return Err(error(
+                                                escape_index + 1,
+                                                "unterminated unicode escape",
+                                            ));
+                                        }
+                                    }
+                                }
+                                u32::from_str_radix(&digits, 16)
+                                    .ok()
+                                    .and_then(char::from_u32)
+                                    .ok_or_else(|| {
+                                        error(escape_index + 1, "invalid unicode escape")
+                                    })?
+                            }
+                            _ => return Err(error(escape_index + 1, "invalid escape sequence")),
+                        };
+                        value.push(escaped);
+                    }
+                    Some((_, c)) => value.push(c),
+                    None => return Err(error(column, "unterminated string literal")),
+                }
+            }
+
+            tokens.push(Token {
+                kind: TokenKind::Str(value),
+                column,
+            });
+        } else {
+            let mut word = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if c.is_whitespace() || c == ';' || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+
+            tokens.push(Token {
+                kind: TokenKind::Word(word),
+                column,
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Operands<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    line: usize,
+    end_column: usize,
+}
+
+impl<'a> Operands<'a> {
+    fn error(&self, column: usize, message: String) -> AsmError {
+        AsmError {
+            line: self.line,
+            column,
+            message,
+        }
+    }
+
+    fn next(&mut self, expected: &str) -> Result<&'a Token, AsmError> {
+        match self.tokens.get(self.position) {
+            Some(token) => {
+                self.position += 1;
+                Ok(token)
+            }
+            None => Err(self.error(self.end_column, format!("expected {}", expected))),
+        }
+    }
+
+    fn word(&mut self, expected: &str) -> Result<(&'a str, usize), AsmError> {
+        let token = self.next(expected)?;
+        match &token.kind {
+            TokenKind::Word(word) => Ok((word, token.column)),
+            TokenKind::Str(_) => Err(self.error(
+                token.column,
+                format!("expected {}, found string literal", expected),
+            )),
+        }
+    }
+
+    fn string(&mut self) -> Result<&'a str, AsmError> {
+        let token = self.next("string literal")?;
+        match &token.kind {
+            TokenKind::Str(value) => Ok(value),
+            TokenKind::Word(word) => Err(self.error(
+                token.column,
+                format!("expected string literal, found '{}'", word),
+            )),
+        }
+    }
+
+    fn name(&mut self) -> Result<String, AsmError> {
+        let token = self.next("name")?;
+        let name = match &token.kind {
+            TokenKind::Word(word) | TokenKind::Str(word) => word,
+        };
+
+        if name.len() > u8::MAX as usize {
+            return Err(self.error(
+                token.column,
+                format!("name is {} bytes long, the maximum is 255", name.len()),
+            ));
+        }
+
+        Ok(name.to_string())
+    }
+
+    fn integer(&mut self) -> Result<i64, AsmError> {
+        let (word, column) = self.word("integer")?;
+        parse_integer(word).ok_or_else(|| self.error(column, format!("invalid integer '{}'", word)))
+    }
+
+    fn big_integer(&mut self) -> Result<BigInt, AsmError> {
+        let (word, column) = self.word("integer")?;
+        word.parse::<BigInt>()
+            .map_err(|_| self.error(column, format!("invalid integer '{}'", word)))
+    }
+
+    fn float(&mut self) -> Result<f64, AsmError> {
+        let (word, column) = self.word("float")?;
+        word.parse::<f64>()
+            .map_err(|_| self.error(column, format!("invalid float '{}'", word)))
+    }
+
+    fn byte(&mut self) -> Result<u8, AsmError> {
+        let (word, column) = self.word("byte")?;
+        parse_integer(word)
+            .and_then(|value| u8::try_from(value).ok())
+            .ok_or_else(|| self.error(column, format!("invalid byte '{}'", word)))
+    }
+
+    fn sub_type(&mut self, table: &[(u8, &str)], expected: &str) -> Result<u8, AsmError> {
+        let (word, column) = self.word(expected)?;
+        code_of(table, word)
+            .or_else(|| parse_integer(word).and_then(|value| u8::try_from(value).ok()))
+            .ok_or_else(|| self.error(column, format!("unknown {} '{}'", expected, word)))
+    }
+
+    fn finish(&self) -> Result<(), AsmError> {
+        match self.tokens.get(self.position) {
+            Some(token) => Err(self.error(token.column, "unexpected operand".to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut bytecode = Vec::new();
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let tokens = tokenize(line, line_number)?;
+
+        let Some((mnemonic, rest)) = tokens.split_first() else {
+            continue;
+        };
+
+        let mut operands = Operands {
+            tokens: rest,
+            position: 0,
+            line: line_number,
+            end_column: line.chars().count() + 1,
+        };
+
+        let opcode = match &mnemonic.kind {
+            TokenKind::Word(word) => code_of(OPCODES, word),
+            TokenKind::Str(_) => None,
+        };
+        let Some(opcode) = opcode else {
+            let found = match &mnemonic.kind {
+                TokenKind::Word(word) => format!("unknown mnemonic '{}'", word),
+                TokenKind::Str(_) => "expected mnemonic, found string literal".to_string(),
+            };
+            return Err(operands.error(mnemonic.column, found));
+        };
+
+        let instruction = match opcode {
+            OP_PUSH => {
+                let (word, column) = operands.word("push type")?;
+                let Some(datatype) = code_of(PUSH_TYPES, word) else {
+                    return Err(operands.error(column, format!("unknown push type '{}'", word)));
+                };
+
+                match datatype {
+                    PUSH_TYPE_INTEGER => Instruction::PushInteger {
+                        value: operands.integer()?,
+                    },
+                    PUSH_TYPE_FLOAT => Instruction::PushFloat {
+                        value: operands.float()?,
+                    },
+                    PUSH_TYPE_STRING => Instruction::PushString {
+                        value: operands.string()?.to_string(),
+                    },
+                    PUSH_TYPE_BOOLEAN => {
+                        let (word, column) = operands.word("boolean")?;
+                        let value = match word {
+                            "true" => true,
+                            "false" => false,
+                            _ => {
+                                return Err(
+                                    operands.error(column, format!("invalid boolean '{}'", word))
+                                );
+                            }
+                        };
+                        Instruction::PushBoolean { value }
+                    }
+                    PUSH_TYPE_INTEGER_POWER => Instruction::PushIntegerPower {
+                        power: operands.byte()?,
+                    },
+                    PUSH_TYPE_INTEGER_POWER_SUB => Instruction::PushIntegerPowerSub {
+                        power: operands.byte()?,
+                    },
+                    PUSH_TYPE_NULL => Instruction::PushNull,
+                    PUSH_TYPE_BIG_INTEGER => Instruction::PushBigInteger {
+                        value: operands.big_integer()?,
+                    },
+                    _ => unreachable!("every type in PUSH_TYPES has an assembler form"),
+                }
+            }
+            OP_LOAD => Instruction::Load {
+                name: operands.name()?,
+            },
+            OP_STORE => Instruction::Store {
+                name: operands.name()?,
+            },
+            OP_FREE => Instruction::Free {
+                name: operands.name()?,
+            },
+            OP_LOAD_GLOBAL => Instruction::LoadGlobal {
+                name: operands.name()?,
+            },
+            OP_STORE_GLOBAL => Instruction::StoreGlobal {
+                name: operands.name()?,
+            },
+            OP_LOAD_OR_NULL => Instruction::LoadOrNull {
+                name: operands.name()?,
+            },
+            OP_IS_NULL => Instruction::IsNull,
+            OP_LABEL => Instruction::Label {
+                name: operands.name()?,
+            },
+            OP_JUMP => Instruction::Jump {
+                target: Target::new(operands.name()?),
+            },
+            OP_JUMP_IF_TRUE => Instruction::JumpIfTrue {
+                target: Target::new(operands.name()?),
+            },
+            OP_JUMP_IF_FALSE => Instruction::JumpIfFalse {
+                target: Target::new(operands.name()?),
+            },
+            OP_CALL => Instruction::Call {
+                target: Target::new(operands.name()?),
+            },
+            OP_FUNC => Instruction::Func {
+                name: operands.name()?,
+                args: operands.byte()?,
+                returns: operands.byte()?,
+            },
+            OP_CAST => Instruction::Cast {
+                cast_type: operands.sub_type(CAST_TYPES, "cast type")?,
+            },
+            OP_MATH => Instruction::Math {
+                math_type: operands.sub_type(MATH_TYPES, "math type")?,
+            },
+            OP_CMP => Instruction::Cmp {
+                cmp_type: operands.sub_type(CMP_TYPES, "comparison type")?,
+            },
+            OP_DEBUG => Instruction::Debug,
+            OP_DUP => Instruction::Dup,
+            OP_SWAP => Instruction::Swap,
+            OP_POP => Instruction::Pop,
+            OP_LOAD_REF => Instruction::LoadRef,
+            OP_STORE_REF => Instruction::StoreRef,
+            OP_ADD => Instruction::Add,
+            OP_SUB => Instruction::Sub,
+            OP_MUL => Instruction::Mul,
+            OP_DIV => Instruction::Div,
+            OP_MOD => Instruction::Mod,
+            OP_ADD_WRAPPING => Instruction::AddWrapping,
+            OP_SUB_WRAPPING => Instruction::SubWrapping,
+            OP_MUL_WRAPPING => Instruction::MulWrapping,
+            OP_ADD_SATURATING => Instruction::AddSaturating,
+            OP_SUB_SATURATING => Instruction::SubSaturating,
+            OP_MUL_SATURATING => Instruction::MulSaturating,
+            OP_STR_GET_SLICE => Instruction::StrGetSlice,
+            OP_STR_LENGTH => Instruction::StrLength,
+            OP_STR_FIND => Instruction::StrFind,
+            OP_STR_CONTAINS => Instruction::StrContains,
+            OP_STR_STARTS_WITH => Instruction::StrStartsWith,
+            OP_STR_ENDS_WITH => Instruction::StrEndsWith,
+            OP_STR_REPLACE => Instruction::StrReplace,
+            OP_STR_SPLIT => Instruction::StrSplit,
+            OP_STR_JOIN => Instruction::StrJoin,
+            OP_STR_UPPER => Instruction::StrUpper,
+            OP_STR_LOWER => Instruction::StrLower,
+            OP_STR_TRIM => Instruction::StrTrim,
+            OP_STR_REPEAT => Instruction::StrRepeat,
+            OP_LIST_NEW => Instruction::ListNew,
+            OP_LIST_PUSH => Instruction::ListPush,
+            OP_LIST_POP => Instruction::ListPop,
+            OP_LIST_GET => Instruction::ListGet,
+            OP_LIST_SET => Instruction::ListSet,
+            OP_LIST_LENGTH => Instruction::ListLength,
+            OP_LIST_SLICE => Instruction::ListSlice,
+            OP_MAP_NEW => Instruction::MapNew,
+            OP_MAP_GET => Instruction::MapGet,
+            OP_MAP_SET => Instruction::MapSet,
+            OP_MAP_DELETE => Instruction::MapDelete,
+            OP_MAP_CONTAINS => Instruction::MapContains,
+            OP_MAP_KEYS => Instruction::MapKeys,
+            OP_AND => Instruction::And,
+            OP_OR => Instruction::Or,
+            OP_XOR => Instruction::Xor,
+            OP_NOT => Instruction::Not,
+            OP_SHL => Instruction::Shl,
+            OP_SHR => Instruction::Shr,
+            OP_RETURN => Instruction::Return,
+            OP_DISPLAY_STDOUT => Instruction::DisplayStdout,
+            OP_DISPLAY_STDERR => Instruction::DisplayStderr,
+            OP_INPUT => Instruction::Input,
+            OP_EXIT => Instruction::Exit,
+            _ => unreachable!("every opcode in OPCODES has an assembler form"),
+        };
+
+        operands.finish()?;
+        instruction.encode(&mut bytecode);
+    }
+
+    Ok(bytecode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(source: &str) -> (usize, usize, String) {
+        let err = assemble(source).unwrap_err();
+        (err.line, err.column, err.message)
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_token() {
+        let long_name = format!("LOAD {}", "x".repeat(256));
+        let cases = [
+            ("NOPE", 1, 1, "unknown mnemonic 'NOPE'"),
+            ("\"DUP\"", 1, 1, "expected mnemonic, found string literal"),
+            ("DUP\n\n    PUSH WHAT 1", 3, 10, "unknown push type 'WHAT'"),
+            ("LOAD", 1, 5, "expected name"),
+            ("PUSH INT 12x", 1, 10, "invalid integer '12x'"),
+            ("PUSH INT_POW 256", 1, 14, "invalid byte '256'"),
+            ("CAST NOPE", 1, 6, "unknown cast type 'NOPE'"),
+            ("DUP 1", 1, 5, "unexpected operand"),
+            ("PUSH STRING \"abc", 1, 13, "unterminated string literal"),
+            ("PUSH STRING \"a\\q\"", 1, 15, "invalid escape sequence"),
+            (
+                "PUSH STRING \"\\u{110000}\"",
+                1,
+                14,
+                "invalid unicode escape",
+            ),
+            (
+                &long_name,
+                1,
+                6,
+                "name is 256 bytes long, the maximum is 255",
+            ),
+        ];
+
+        for (source, line, column, message) in cases {
+            assert_eq!(
+                error(source),
+                (line, column, message.to_string()),
+                "{:?}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        assert_eq!(
+            error("PUSH STRING \"\u{E9}\u{E9}\" 5"),
+            (1, 18, "unexpected operand".to_string())
+        );
+    }
+}
@@ -0,0 +1,617 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    ops::{Add, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+/// An arbitrary-precision signed integer.
+///
+/// The value is kept as a sign and a magnitude of little-endian base 2^32
+/// limbs. The magnitude never ends in a zero limb and zero is never negative,
+/// so two values are equal exactly when their representations are.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+/// The largest big integer the VM works with, in bits. Multiplication and
+/// division take time quadratic in the size of their operands, so the cap
+/// also bounds how long a single instruction can take.
+pub(crate) const MAX_BITS: u64 = 1 << 18;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBigIntError;
+
+impl fmt::Display for ParseBigIntError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid integer literal")
+    }
+}
+
+impl std::error::Error for ParseBigIntError {}
+
+impl BigInt {
+    fn from_parts(negative: bool, mut magnitude: Vec<u32>) -> Self {
+        trim(&mut magnitude);
+        Self {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// The number of bits in the magnitude, which is zero for zero.
+    pub fn bits(&self) -> u64 {
+        match self.magnitude.last() {
+            Some(top) => self.magnitude.len() as u64 * 32 - top.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    /// Returns the value as an `i64`, or `None` if it does not fit.
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+
+        let low = self.magnitude.first().copied().unwrap_or(0) as u64;
+        let high = self.magnitude.get(1).copied().unwrap_or(0) as u64;
+        let magnitude = (high << 32) | low;
+
+        if self.negative {
+            0i64.checked_sub_unsigned(magnitude)
+        } else {
+            i64::try_from(magnitude).ok()
+        }
+    }
+
+    /// Divides by `other`, truncating towards zero like `i64` division. The
+    /// remainder takes the sign of the dividend. Returns `None` if `other` is
+    /// zero.
+    pub fn divrem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {
+            return None;
+        }
+
+        let (quotient, remainder) = divrem_magnitude(&self.magnitude, &other.magnitude);
+        Some((
+            BigInt::from_parts(self.negative != other.negative, quotient),
+            BigInt::from_parts(self.negative, remainder),
+        ))
+    }
+
+    /// Encodes the value as little-endian two's complement bytes, using as
+    /// few bytes as possible.
+    pub fn to_signed_bytes_le(&self) -> Vec<u8> {
+        let mut bytes = self
+            .magnitude
+            .iter()
+            .flat_map(|limb| limb.to_le_bytes())
+            .collect::<Vec<u8>>();
+        while bytes.last() == Some(&0) {
+            bytes.pop();
+        }
+
+        if self.negative {
+            negate_bytes(&mut bytes);
+            if bytes.last().is_none_or(|byte| byte & 0x80 == 0) {
+                bytes.push(0xFF);
+            }
+            while bytes.len() > 1
+                && bytes[bytes.len() - 1] == 0xFF
+                && bytes[bytes.len() - 2] & 0x80 != 0
+            {
+                bytes.pop();
+            }
+        } else if bytes.last().is_some_and(|byte| byte & 0x80 != 0) {
+            bytes.push(0x00);
+        }
+
+        bytes
+    }
+
+    /// Decodes little-endian two's complement bytes, the inverse of
+    /// `to_signed_bytes_le`.
+    pub fn from_signed_bytes_le(bytes: &[u8]) -> Self {
+        let negative = bytes.last().is_some_and(|byte| byte & 0x80 != 0);
+
+        let mut bytes = bytes.to_vec();
+        if negative {
+            negate_bytes(&mut bytes);
+        }
+
+        let magnitude = bytes
+            .chunks(4)
+            .map(|chunk| {
+                let mut limb = [0u8; 4];
+                limb[..chunk.len()].copy_from_slice(chunk);
+                u32::from_le_bytes(limb)
+            })
+            .collect();
+
+        BigInt::from_parts(negative, magnitude)
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let magnitude = value.unsigned_abs();
+        BigInt::from_parts(value < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.magnitude, &other.magnitude),
+            (true, true) => cmp_magnitude(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_parts(
+                self.negative,
+                add_magnitude(&self.magnitude, &other.magnitude),
+            );
+        }
+
+        match cmp_magnitude(&self.magnitude, &other.magnitude) {
+            Ordering::Less => BigInt::from_parts(
+                other.negative,
+                sub_magnitude(&other.magnitude, &self.magnitude),
+            ),
+            _ => BigInt::from_parts(
+                self.negative,
+                sub_magnitude(&self.magnitude, &other.magnitude),
+            ),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::from_parts(
+            self.negative != other.negative,
+            mul_magnitude(&self.magnitude, &other.magnitude),
+        )
+    }
+}
+
+/// The largest power of ten that fits in a limb, used to convert to and from
+/// decimal nine digits at a time.
+const DECIMAL_CHUNK: u32 = 1_000_000_000;
+const DECIMAL_CHUNK_DIGITS: usize = 9;
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        let mut chunks = Vec::new();
+        let mut magnitude = self.magnitude.clone();
+        while !magnitude.is_empty() {
+            let (quotient, remainder) = divrem_limb(&magnitude, DECIMAL_CHUNK);
+            chunks.push(remainder);
+            magnitude = quotient;
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+
+        let mut chunks = chunks.iter().rev();
+        if let Some(first) = chunks.next() {
+            write!(f, "{}", first)?;
+        }
+        for chunk in chunks {
+            write!(f, "{:09}", chunk)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for BigInt {
+    type Err = ParseBigIntError;
+
+    /// Parses a decimal integer with an optional leading `+` or `-`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+
+        if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(ParseBigIntError);
+        }
+
+        let mut magnitude = Vec::new();
+        let first_chunk = match digits.len() % DECIMAL_CHUNK_DIGITS {
+            0 => DECIMAL_CHUNK_DIGITS,
+            len => len,
+        };
+
+        let mut start = 0;
+        let mut end = first_chunk;
+        while start < digits.len() {
+            let chunk = &digits[start..end];
+            let scale = 10u32.pow(chunk.len() as u32);
+            mul_add_limb(&mut magnitude, scale, chunk.parse().unwrap());
+
+            start = end;
+            end += DECIMAL_CHUNK_DIGITS;
+        }
+
+        Ok(BigInt::from_parts(negative, magnitude))
+    }
+}
+
+fn trim(magnitude: &mut Vec<u32>) {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+}
+
+fn negate_bytes(bytes: &mut [u8]) {
+    let mut carry = true;
+    for byte in bytes {
+        let (value, overflow) = (!*byte).overflowing_add(carry as u8);
+        *byte = value;
+        carry = overflow;
+    }
+}
+
+fn cmp_magnitude(lhs: &[u32], rhs: &[u32]) -> Ordering {
+    lhs.len()
+        .cmp(&rhs.len())
+        .then_with(|| lhs.iter().rev().cmp(rhs.iter().rev()))
+}
+
+fn add_magnitude(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    let (long, short) = if lhs.len() >= rhs.len() {
+        (lhs, rhs)
+    } else {
+        (rhs, lhs)
+    };
+
+    let mut result = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (index, limb) in long.iter().enumerate() {
+        let sum = *limb as u64 + short.get(index).copied().unwrap_or(0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+
+    result
+}
+
+/// Subtracts `rhs` from `lhs`, which must not be the smaller of the two.
+fn sub_magnitude(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(lhs.len());
+    let mut borrow = false;
+    for (index, limb) in lhs.iter().enumerate() {
+        let (value, first) = limb.overflowing_sub(rhs.get(index).copied().unwrap_or(0));
+        let (value, second) = value.overflowing_sub(borrow as u32);
+        result.push(value);
+        borrow = first || second;
+    }
+
+    trim(&mut result);
+    result
+}
+
+fn mul_magnitude(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    if lhs.is_empty() || rhs.is_empty() {
+        return Vec::new();
+    }
+
+    let mut result = vec![0u32; lhs.len() + rhs.len()];
+    for (i, a) in lhs.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, b) in rhs.iter().enumerate() {
+            let product = *a as u64 * *b as u64 + result[i + j] as u64 + carry;
+            result[i + j] = product as u32;
+            carry = product >> 32;
+        }
+        result[i + rhs.len()] = carry as u32;
+    }
+
+    trim(&mut result);
+    result
+}
+
+/// Multiplies `magnitude` by `scale` and adds `addend`, in place.
+fn mul_add_limb(magnitude: &mut Vec<u32>, scale: u32, addend: u32) {
+    let mut carry = addend as u64;
+    for limb in magnitude.iter_mut() {
+        let value = *limb as u64 * scale as u64 + carry;
+        *limb = value as u32;
+        carry = value >> 32;
+    }
+    if carry > 0 {
+        magnitude.push(carry as u32);
+    }
+}
+
+fn divrem_limb(lhs: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0u32; lhs.len()];
+    let mut remainder = 0u64;
+    for (index, limb) in lhs.iter().enumerate().rev() {
+        let value = (remainder << 32) | *limb as u64;
+        quotient[index] = (value / divisor as u64) as u32;
+        remainder = value % divisor as u64;
+    }
+
+    trim(&mut quotient);
+    (quotient, remainder as u32)
+}
+
+fn shl_magnitude(magnitude: &[u32], shift: u32) -> Vec<u32> {
+    if shift == 0 {
+        return magnitude.to_vec();
+    }
+
+    let mut result = Vec::with_capacity(magnitude.len() + 1);
+    let mut carry = 0u32;
+    for limb in magnitude {
+        result.push((limb << shift) | carry);
+        carry = limb >> (32 - shift);
+    }
+    result.push(carry);
+    result
+}
+
+fn shr_magnitude(magnitude: &[u32], shift: u32) -> Vec<u32> {
+    if shift == 0 {
+        return magnitude.to_vec();
+    }
+
+    let mut result = vec![0u32; magnitude.len()];
+    for index in 0..magnitude.len() {
+        let high = magnitude
+            .get(index + 1)
+            .map_or(0, |limb| limb << (32 - shift));
+        result[index] = (magnitude[index] >> shift) | high;
+    }
+    result
+}
+
+/// Long division of magnitudes (Knuth, TAOCP vol. 2, algorithm D). `rhs` must
+/// not be empty.
+fn divrem_magnitude(lhs: &[u32], rhs: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_magnitude(lhs, rhs) == Ordering::Less {
+        return (Vec::new(), lhs.to_vec());
+    }
+
+    if let [divisor] = rhs {
+        let (quotient, remainder) = divrem_limb(lhs, *divisor);
+        let mut remainder = vec![remainder];
+        trim(&mut remainder);
+        return (quotient, remainder);
+    }
+
+    // Normalise so the divisor's top limb has its high bit set, which keeps
+    // each quotient digit estimate within two of the true value.
+    let shift = rhs[rhs.len() - 1].leading_zeros();
+    let divisor = shl_magnitude(rhs, shift);
+    let divisor = &divisor[..rhs.len()];
+    let mut remainder = shl_magnitude(lhs, shift);
+    if remainder.len() == lhs.len() {
+        remainder.push(0);
+    }
+
+    let n = divisor.len();
+    let m = remainder.len() - n;
+    let top = divisor[n - 1] as u64;
+    let next = divisor[n - 2] as u64;
+    let mut quotient = vec![0u32; m];
+
+    for j in (0..m).rev() {
+        let numerator = ((remainder[j + n] as u64) << 32) | remainder[j + n - 1] as u64;
+        let mut estimate = numerator / top;
+        let mut rest = numerator % top;
+        while estimate > u32::MAX as u64
+            || estimate * next > ((rest << 32) | remainder[j + n - 2] as u64)
+        {
+            estimate -= 1;
+            rest += top;
+            if rest > u32::MAX as u64 {
+                break;
+            }
+        }
+
+        let mut borrow = 0i64;
+        let mut carry = 0u64;
+        for i in 0..n {
+            let product = estimate * divisor[i] as u64 + carry;
+            carry = product >> 32;
+            let value = remainder[i + j] as i64 - borrow - (product & 0xFFFF_FFFF) as i64;
+            remainder[i + j] = value as u32;
+            borrow = (value < 0) as i64;
+        }
+        let value = remainder[j + n] as i64 - borrow - carry as i64;
+        remainder[j + n] = value as u32;
+
+        // The estimate was one too large; add the divisor back.
+        if value < 0 {
+            estimate -= 1;
+            let mut carry = 0u64;
+            for i in 0..n {
+                let sum = remainder[i + j] as u64 + divisor[i] as u64 + carry;
+                remainder[i + j] = sum as u32;
+                carry = sum >> 32;
+            }
+            remainder[j + n] = remainder[j + n].wrapping_add(carry as u32);
+        }
+
+        quotient[j] = estimate as u32;
+    }
+
+    remainder.truncate(n);
+    let mut remainder = shr_magnitude(&remainder, shift);
+    trim(&mut quotient);
+    trim(&mut remainder);
+    (quotient, remainder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(text: &str) -> BigInt {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn divrem_truncates_like_i64() {
+        for (lhs, rhs) in [
+            (7, 2),
+            (-7, 2),
+            (7, -2),
+            (-7, -2),
+            (6, 3),
+            (-6, 3),
+            (0, 5),
+            (1, -5),
+        ] {
+            let (quotient, remainder) = BigInt::from(lhs).divrem(&BigInt::from(rhs)).unwrap();
+            assert_eq!(quotient, BigInt::from(lhs / rhs), "{} / {}", lhs, rhs);
+            assert_eq!(remainder, BigInt::from(lhs % rhs), "{} % {}", lhs, rhs);
+        }
+
+        assert_eq!(BigInt::from(1).divrem(&BigInt::default()), None);
+    }
+
+    #[test]
+    fn divrem_multi_limb() {
+        let lhs = big("-123456789012345678901234567890123456789");
+        let rhs = big("98765432109876543210987");
+        let (quotient, remainder) = lhs.divrem(&rhs).unwrap();
+
+        assert_eq!(quotient, big("-1249999988609375"));
+        assert_eq!(remainder, big("-14063317902772253664"));
+        assert_eq!(&(&quotient * &rhs) + &remainder, lhs);
+    }
+
+    #[test]
+    fn divrem_adds_back_an_overestimated_digit() {
+        // Both divisions overestimate a quotient digit by one, so the divisor
+        // has to be added back to the partial remainder.
+        let lhs = big("79228162514264337593543950336");
+        let cases = [
+            (
+                "39614081257132168796771975169",
+                "1",
+                "39614081257132168796771975167",
+            ),
+            ("36893488147419103233", "2147483647", "36893488145271619585"),
+        ];
+
+        for (rhs, quotient, remainder) in cases {
+            assert_eq!(lhs.divrem(&big(rhs)), Some((big(quotient), big(remainder))));
+        }
+    }
+
+    #[test]
+    fn signed_bytes_round_trip() {
+        let cases: [(i64, &[u8]); 10] = [
+            (0, &[]),
+            (-1, &[0xFF]),
+            (127, &[0x7F]),
+            (128, &[0x80, 0x00]),
+            (-128, &[0x80]),
+            (-129, &[0x7F, 0xFF]),
+            (255, &[0xFF, 0x00]),
+            (-256, &[0x00, 0xFF]),
+            (-32768, &[0x00, 0x80]),
+            (-32769, &[0xFF, 0x7F, 0xFF]),
+        ];
+
+        for (value, bytes) in cases {
+            let value = BigInt::from(value);
+            assert_eq!(value.to_signed_bytes_le(), bytes, "{}", value);
+            assert_eq!(BigInt::from_signed_bytes_le(bytes), value);
+        }
+
+        for text in [
+            "-2147483648",
+            "-2147483649",
+            "-9223372036854775808",
+            "-18446744073709551616",
+            "-18446744073709551615",
+            "-340282366920938463463374607431768211456",
+        ] {
+            let value = big(text);
+            assert_eq!(
+                BigInt::from_signed_bytes_le(&value.to_signed_bytes_le()),
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn display_and_parse_round_trip() {
+        for text in [
+            "0",
+            "-1",
+            "999999999",
+            "1000000000",
+            "-1000000000000000000000000000001",
+            "340282366920938463463374607431768211456",
+        ] {
+            assert_eq!(big(text).to_string(), text);
+        }
+
+        assert_eq!(big("+5").to_string(), "5");
+        assert_eq!(big("-0").to_string(), "0");
+        assert_eq!(big("000123").to_string(), "123");
+
+        for text in ["", "-", "1a", "--1", " 1", "0x10"] {
+            assert_eq!(text.parse::<BigInt>(), Err(ParseBigIntError), "{:?}", text);
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+};
+
+use ivm::{ExitStatus, IVMType, Instruction, VM};
+
+use crate::USAGE;
+
+const HELP: &str = "Commands:
+    b, break <offset|label>    Stop before an instruction, or the first one after a label
+    d, delete <offset|label>   Remove a breakpoint
+    breakpoints                List breakpoints
+    s, step                    Run one instruction
+    n, next                    Run one instruction, stepping over CALL
+    c, continue                Run until a breakpoint or the end of the program
+    stack                      Print the stack, top last
+    v, vars                    Print global variables and the current call's locals
+    f, frames                  Print the active calls, innermost first
+    l, list                    Disassemble around the next instruction
+    h, help                    Print this list
+    q, quit                    Leave the debugger";
+
+/// How many instructions `list` shows on either side of the next one.
+const LIST_CONTEXT: usize = 5;
+
+struct Debugger {
+    vm: VM,
+    /// Indices of the instructions to stop before.
+    breakpoints: BTreeSet<usize>,
+    finished: bool,
+}
+
+impl Debugger {
+    fn command(&mut self, command: &str, argument: Option<&str>) -> Result<(), String> {
+        match (command, argument) {
+            ("b" | "break", Some(location)) => {
+                let index = self.resolve(location)?;
+                self.breakpoints.insert(index);
+                println!("Breakpoint at {}", self.describe_index(index));
+            }
+            ("d" | "delete", Some(location)) => {
+                let index = self.resolve(location)?;
+                if !self.breakpoints.remove(&index) {
+                    return Err(format!("no breakpoint at {}", self.describe_index(index)));
+                }
+            }
+            ("breakpoints", None) => {
+                if self.breakpoints.is_empty() {
+                    println!("No breakpoints");
+                }
+                for index in &self.breakpoints {
+                    println!("{}", self.describe_index(*index));
+                }
+            }
+            ("s" | "step", None) => {
+                self.check_running()?;
+                if self.step() {
+                    self.print_instruction(self.vm.pc());
+                }
+            }
+            ("n" | "next", None) => {
+                self.check_running()?;
+                let instructions = self.vm.program().instructions();
+                match instructions.get(self.vm.pc()) {
+                    Some((_, Instruction::Call { .. })) => {
+                        let depth = self.vm.frames().len();
+                        self.run_until(|vm| vm.frames().len() <= depth);
+                    }
+                    _ => {
+                        if self.step() {
+                            self.print_instruction(self.vm.pc());
+                        }
+                    }
+                }
+            }
+            ("c" | "continue", None) => {
+                self.check_running()?;
+                self.run_until(|_| false);
+            }
+            ("stack", None) => {
+                if self.vm.stack().is_empty() {
+                    println!("Stack is empty");
+                }
+                for (index, value) in self.vm.stack().iter().enumerate() {
+                    println!("{:>4}: {}", index, describe_value(value));
+                }
+            }
+            ("v" | "vars", None) => {
+                println!("Globals:");
+                print_variables(self.vm.variables().iter());
+                if let Some(frame) = self.vm.frames().last() {
+                    println!("Locals:");
+                    print_variables(frame.locals().iter());
+                }
+            }
+            ("f" | "frames", None) => {
+                if self.vm.frames().is_empty() {
+                    println!("No active calls");
+                }
+                let instructions = self.vm.program().instructions();
+                for (depth, frame) in self.vm.frames().iter().rev().enumerate() {
+                    let (offset, call) = &instructions[frame.return_address() - 1];
+                    let name = call.target().map_or("?", |target| target.label.as_str());
+                    println!("#{} {}, called from {:08X}", depth, name, offset);
+                }
+            }
+            ("l" | "list", None) => {
+                let pc = self.vm.pc();
+                let end = (pc + LIST_CONTEXT + 1).min(self.vm.program().instructions().len());
+                for index in pc.saturating_sub(LIST_CONTEXT)..end {
+                    self.print_instruction(index);
+                }
+            }
+            ("h" | "help", None) => println!("{}", HELP),
+            _ => return Err(format!("unknown command '{}', try 'help'", command)),
+        }
+
+        Ok(())
+    }
+
+    /// Finds the instruction a breakpoint location refers to. `LABEL` and
+    /// `FUNC` are skipped over by jumps and calls, so a breakpoint on one is
+    /// placed on the instruction after it.
+    fn resolve(&self, location: &str) -> Result<usize, String> {
+        let program = self.vm.program();
+        let index = match program.labels().get(location) {
+            Some(index) => *index,
+            None => {
+                let offset = parse_offset(location)
+                    .ok_or_else(|| format!("'{}' is not a label or an offset", location))?;
+                program
+                    .instructions()
+                    .iter()
+                    .position(|(start, _)| *start == offset)
+                    .ok_or_else(|| format!("no instruction starts at {:08X}", offset))?
+            }
+        };
+
+        match program.instructions()[index].1 {
+            Instruction::Label { .. } | Instruction::Func { .. } => Ok(index + 1),
+            _ => Ok(index),
+        }
+    }
+
+    fn check_running(&self) -> Result<(), String> {
+        if self.finished {
+            return Err("the program has finished".to_string());
+        }
+        Ok(())
+    }
+
+    /// Runs one instruction. Returns false, after reporting why, once the
+    /// program has finished.
+    fn step(&mut self) -> bool {
+        match self.vm.step() {
+            Ok(None) => return true,
+            Ok(Some(ExitStatus::Exited(code))) => println!("Program exited with code {}", code),
+            Ok(Some(_)) => println!("Program halted"),
+            Err(err) => println!("Error: {}", err),
+        }
+
+        self.finished = true;
+        false
+    }
+
+    /// Steps until `done` holds or a breakpoint is reached.
+    fn run_until(&mut self, done: impl Fn(&VM) -> bool) {
+        while self.step() {
+            let pc = self.vm.pc();
+            if self.breakpoints.contains(&pc) {
+                println!("Breakpoint at {}", self.describe_index(pc));
+                self.print_instruction(pc);
+                return;
+            }
+            if done(&self.vm) {
+                self.print_instruction(pc);
+                return;
+            }
+        }
+    }
+
+    fn describe_index(&self, index: usize) -> String {
+        match self.vm.program().instructions().get(index) {
+            Some((offset, _)) => format!("{:08X}", offset),
+            None => "the end of the program".to_string(),
+        }
+    }
+
+    fn print_instruction(&self, index: usize) {
+        let Some((offset, instruction)) = self.vm.program().instructions().get(index) else {
+            return;
+        };
+
+        let marker = if index == self.vm.pc() { "=>" } else { "  " };
+        let breakpoint = if self.breakpoints.contains(&index) {
+            '*'
+        } else {
+            ' '
+        };
+        let text = match instruction {
+            Instruction::Label { .. } | Instruction::Func { .. } => instruction.to_string(),
+            _ => format!("    {}", instruction),
+        };
+
+        println!("{}{} {:08X} {}", marker, breakpoint, offset, text);
+    }
+}
+
+/// Parses an offset as hexadecimal with a `0x` prefix, or decimal without.
+fn parse_offset(text: &str) -> Option<usize> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn describe_value(value: &IVMType) -> String {
+    match value {
+        IVMType::String { value } => format!("String {:?}", value),
+        IVMType::Null => "Null".to_string(),
+        value => format!("{} {}", value.type_name(), value),
+    }
+}
+
+fn print_variables<'a>(variables: impl Iterator<Item = (&'a String, &'a IVMType)>) {
+    let mut variables = variables.collect::<Vec<_>>();
+    variables.sort_by_key(|(name, _)| *name);
+
+    if variables.is_empty() {
+        println!("    (none)");
+    }
+    for (name, value) in variables {
+        println!("    {} = {}", name, describe_value(value));
+    }
+}
+
+pub fn debug(args: &[String]) -> Result<(), String> {
+    let [filename] = args else {
+        return Err(USAGE.to_string());
+    };
+
+    let data =
+        std::fs::read(filename).map_err(|e| format!("Failed to read {}: {}", filename, e))?;
+
+    let program = VM::load(&data).map_err(|e| e.to_string())?;
+    let count = program.instructions().len();
+    let mut debugger = Debugger {
+        vm: VM::new(program),
+        breakpoints: BTreeSet::new(),
+        finished: count == 0,
+    };
+
+    println!(
+        "{} instructions. Type 'help' for a list of commands.",
+        count
+    );
+    debugger.print_instruction(0);
+
+    loop {
+        print!("(ivm) ");
+        io::stdout().flush().map_err(|e| e.to_string())?;
+
+        let mut line = String::new();
+        if io::stdin()
+            .read_line(&mut line)
+            .map_err(|e| e.to_string())?
+            == 0
+        {
+            println!();
+            return Ok(());
+        }
+
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            continue;
+        };
+        if matches!(command, "q" | "quit") {
+            return Ok(());
+        }
+
+        let argument = words.next();
+        if words.next().is_some() {
+            println!("Error: too many arguments, try 'help'");
+            continue;
+        }
+
+        if let Err(err) = debugger.command(command, argument) {
+            println!("Error: {}", err);
+        }
+    }
+}
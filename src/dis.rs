@@ -0,0 +1,179 @@
+use std::fmt::Write;
+
+use crate::instruction::{DecodeError, Instruction, decode};
+
+/// Renders bytecode as assembly that `assemble` accepts. Each line carries the
+/// instruction's offset in a trailing comment, along with the offset of the
+/// `LABEL` it refers to for jumps and calls.
+pub fn disassemble(bytecode: &[u8]) -> Result<String, DecodeError> {
+    let instructions = decode(bytecode)?;
+
+    let mut output = String::new();
+    for (offset, instruction) in &instructions {
+        let text = match instruction {
+            Instruction::Label { .. } | Instruction::Func { .. } => instruction.to_string(),
+            _ => format!("    {}", instruction),
+        };
+
+        let comment = match instruction.target().map(|target| target.index) {
+            Some(Some(index)) => format!("{:08X} -> {:08X}", offset, instructions[index].0),
+            Some(None) => format!("{:08X} -> ?", offset),
+            None => format!("{:08X}", offset),
+        };
+
+        writeln!(output, "{:<40} ; {}", text, comment).unwrap();
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{asm::assemble, opcode::*};
+
+    /// Every instruction form except the sub-types, which `source` adds from
+    /// their tables. Names and strings include characters that need quoting or
+    /// escaping.
+    const SOURCE: &str = r#"
+DEBUG
+PUSH INT -9223372036854775808
+PUSH INT 0x7FFFFFFFFFFFFFFF
+PUSH FLOAT 1.5
+PUSH FLOAT -0.0
+PUSH FLOAT NaN
+PUSH FLOAT inf
+PUSH FLOAT -inf
+PUSH FLOAT 1e300
+PUSH STRING ""
+PUSH STRING "quote \" backslash \\ tab \t newline \n return \r nul \0"
+PUSH STRING "emoji \u{1F600} bell \u{7} delete \u{7F}"
+PUSH BOOL true
+PUSH BOOL false
+PUSH INT_POW 62
+PUSH INT_POW_SUB 63
+PUSH NULL
+PUSH BIGINT -123456789012345678901234567890123456789
+PUSH BIGINT 0
+LOAD x
+STORE "my var"
+LOAD_GLOBAL "\u{E9}t\u{E9}"
+STORE_GLOBAL ""
+LOAD_OR_NULL "semi;colon"
+FREE x
+IS_NULL
+LOAD_REF
+STORE_REF
+DUP
+SWAP
+POP
+ADD
+SUB
+MUL
+DIV
+MOD
+ADD_WRAPPING
+SUB_WRAPPING
+MUL_WRAPPING
+ADD_SATURATING
+SUB_SATURATING
+MUL_SATURATING
+STR_GET_SLICE
+STR_LENGTH
+STR_FIND
+STR_CONTAINS
+STR_STARTS_WITH
+STR_ENDS_WITH
+STR_REPLACE
+STR_SPLIT
+STR_JOIN
+STR_UPPER
+STR_LOWER
+STR_TRIM
+STR_REPEAT
+LIST_NEW
+LIST_PUSH
+LIST_POP
+LIST_GET
+LIST_SET
+LIST_LENGTH
+LIST_SLICE
+MAP_NEW
+MAP_GET
+MAP_SET
+MAP_DELETE
+MAP_CONTAINS
+MAP_KEYS
+AND
+OR
+XOR
+NOT
+SHL
+SHR
+LABEL "loop start"
+JMP "loop start"
+JMP_IF_TRUE "loop start"
+JMP_IF_FALSE end
+CALL "my func"
+FUNC "my func" 2 255
+RET
+DISPLAY_STDOUT
+DISPLAY_STDERR
+INPUT
+EXIT
+LABEL end
+"#;
+
+    fn source() -> String {
+        let mut source = SOURCE.to_string();
+        for (mnemonic, table) in [
+            ("CAST", CAST_TYPES),
+            ("MATH", MATH_TYPES),
+            ("CMP", CMP_TYPES),
+        ] {
+            for (_, name) in table {
+                writeln!(source, "{} {}", mnemonic, name).unwrap();
+            }
+        }
+        source
+    }
+
+    #[test]
+    fn source_covers_every_opcode() {
+        let bytecode = assemble(&source()).unwrap();
+        let opcodes = decode(&bytecode)
+            .unwrap()
+            .iter()
+            .map(|(_, instruction)| instruction.opcode())
+            .collect::<HashSet<u8>>();
+
+        for (opcode, name) in OPCODES {
+            assert!(opcodes.contains(opcode), "{} is not covered", name);
+        }
+    }
+
+    #[test]
+    fn output_assembles_to_the_same_bytecode() {
+        let bytecode = assemble(&source()).unwrap();
+        let listing = disassemble(&bytecode).unwrap();
+
+        assert_eq!(assemble(&listing).unwrap(), bytecode, "{}", listing);
+    }
+
+    #[test]
+    fn comments_give_offsets_and_targets() {
+        let bytecode = assemble("LABEL top\nJMP top\nJMP_IF_TRUE missing").unwrap();
+        let listing = disassemble(&bytecode).unwrap();
+        let comments = listing
+            .lines()
+            .map(|line| line.split_once("; ").unwrap().1)
+            .collect::<Vec<&str>>();
+
+        assert_eq!(
+            comments,
+            ["00000000", "00000005 -> 00000000", "0000000A -> ?"]
+        );
+    }
+}
@@ -0,0 +1,241 @@
+use std::fmt;
+
+use crate::{bigint::MAX_BITS, instruction::DecodeError, opcode::opcode_name};
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    StackUnderflow,
+    StackOverflow(usize),
+    CallStackUnderflow,
+    CallDepthExceeded(usize),
+    TooManyVariables(usize),
+    StringTooLong {
+        length: usize,
+        limit: usize,
+    },
+    StringBytesExceeded(usize),
+    CollectionItemsExceeded(usize),
+    ArgumentCount {
+        function: String,
+        expected: u8,
+        found: usize,
+    },
+    ReturnCount {
+        expected: u8,
+        found: usize,
+    },
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    IncompatibleTypes {
+        lhs: &'static str,
+        rhs: &'static str,
+    },
+    DivisionByZero,
+    IntegerOverflow,
+    BigIntegerTooLarge,
+    FloatOutOfRange(f64),
+    InvalidShift(i64),
+    ParseFailure {
+        text: String,
+        expected: &'static str,
+    },
+    UndefinedVariable(String),
+    UndefinedLabel(String),
+    IndexOutOfBounds {
+        index: i64,
+        length: usize,
+    },
+    EmptyList,
+    KeyNotFound(String),
+    InvalidSlice {
+        start: i64,
+        end: i64,
+        length: usize,
+    },
+    InvalidCast {
+        cast_type: u8,
+        found: &'static str,
+    },
+    InvalidCodepoint(i64),
+    InvalidCharacterCount(usize),
+    InvalidRepeatCount(i64),
+    InvalidComparison(u8),
+    UnorderedComparison,
+    InvalidMathType(u8),
+    InvalidBoolean(u8),
+    UnknownPushType(u8),
+    PermissionDenied(&'static str),
+    UnknownOpcode,
+    Truncated,
+    Io(std::io::Error),
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::StackUnderflow => write!(f, "stack underflow"),
+            ErrorKind::StackOverflow(limit) => {
+                write!(f, "stack depth exceeds the limit of {} values", limit)
+            }
+            ErrorKind::CallStackUnderflow => write!(f, "call stack underflow"),
+            ErrorKind::CallDepthExceeded(limit) => {
+                write!(f, "call depth exceeds the limit of {} frames", limit)
+            }
+            ErrorKind::TooManyVariables(limit) => {
+                write!(f, "variable count exceeds the limit of {}", limit)
+            }
+            ErrorKind::StringTooLong { length, limit } => write!(
+                f,
+                "string of {} bytes exceeds the limit of {} bytes",
+                length, limit
+            ),
+            ErrorKind::StringBytesExceeded(limit) => {
+                write!(f, "strings exceed the limit of {} bytes in total", limit)
+            }
+            ErrorKind::CollectionItemsExceeded(limit) => write!(
+                f,
+                "lists and maps exceed the limit of {} items in total",
+                limit
+            ),
+            ErrorKind::ArgumentCount {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "function '{}' takes {} arguments but only {} are on the stack",
+                function, expected, found
+            ),
+            ErrorKind::ReturnCount { expected, found } => write!(
+                f,
+                "function should return {} values but left {} on the stack",
+                expected, found
+            ),
+            ErrorKind::TypeMismatch { expected, found } => {
+                write!(f, "expected {} on stack, found {}", expected, found)
+            }
+            ErrorKind::IncompatibleTypes { lhs, rhs } => {
+                write!(f, "incompatible types {} and {}", lhs, rhs)
+            }
+            ErrorKind::DivisionByZero => write!(f, "division by zero"),
+            ErrorKind::IntegerOverflow => write!(f, "integer overflow"),
+            ErrorKind::BigIntegerTooLarge => {
+                write!(f, "big integer exceeds the limit of {} bits", MAX_BITS)
+            }
+            ErrorKind::FloatOutOfRange(value) => {
+                write!(f, "float {} cannot be converted to an integer", value)
+            }
+            ErrorKind::InvalidShift(amount) => {
+                write!(f, "shift amount {} is not between 0 and 63", amount)
+            }
+            ErrorKind::ParseFailure { text, expected } => {
+                write!(f, "cannot parse {:?} as {}", text, expected)
+            }
+            ErrorKind::UndefinedVariable(name) => write!(f, "variable '{}' not found", name),
+            ErrorKind::UndefinedLabel(name) => write!(f, "label '{}' not found", name),
+            ErrorKind::IndexOutOfBounds { index, length } => {
+                write!(f, "index {} out of bounds for length {}", index, length)
+            }
+            ErrorKind::EmptyList => write!(f, "list is empty"),
+            ErrorKind::KeyNotFound(key) => write!(f, "key '{}' not found in map", key),
+            ErrorKind::InvalidSlice { start, end, length } => {
+                write!(f, "invalid slice {}..{} of length {}", start, end, length)
+            }
+            ErrorKind::InvalidCast { cast_type, found } => {
+                write!(f, "invalid cast 0x{:02X} from {}", cast_type, found)
+            }
+            ErrorKind::InvalidCodepoint(value) => {
+                write!(f, "{} is not a valid character codepoint", value)
+            }
+            ErrorKind::InvalidCharacterCount(count) => {
+                write!(f, "expected a single character, found {}", count)
+            }
+            ErrorKind::InvalidRepeatCount(count) => {
+                write!(f, "cannot repeat a string {} times", count)
+            }
+            ErrorKind::InvalidComparison(cmp_type) => {
+                write!(f, "invalid comparison type 0x{:02X}", cmp_type)
+            }
+            ErrorKind::UnorderedComparison => write!(f, "NaN cannot be ordered"),
+            ErrorKind::InvalidMathType(math_type) => {
+                write!(f, "invalid math type 0x{:02X}", math_type)
+            }
+            ErrorKind::InvalidBoolean(byte) => write!(f, "invalid boolean value 0x{:02X}", byte),
+            ErrorKind::UnknownPushType(datatype) => {
+                write!(f, "unknown push data type 0x{:02X}", datatype)
+            }
+            ErrorKind::PermissionDenied(action) => write!(f, "{} is not permitted", action),
+            ErrorKind::UnknownOpcode => write!(f, "unknown opcode"),
+            ErrorKind::Truncated => write!(f, "incomplete instruction"),
+            ErrorKind::Io(err) => write!(f, "I/O error: {}", err),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct VmError {
+    pub opcode: u8,
+    pub offset: usize,
+    pub stack_depth: usize,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at 0x{:08X} (0x{:02X} {}, stack depth {})",
+            self.kind,
+            self.offset,
+            self.opcode,
+            opcode_name(self.opcode),
+            self.stack_depth
+        )
+    }
+}
+
+impl std::error::Error for VmError {}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Decode(DecodeError),
+    DuplicateLabel {
+        name: String,
+        offset: usize,
+        previous: usize,
+    },
+    UndefinedLabel {
+        name: String,
+        offset: usize,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Decode(err) => write!(f, "{}", err),
+            LoadError::DuplicateLabel {
+                name,
+                offset,
+                previous,
+            } => write!(
+                f,
+                "label '{}' at 0x{:08X} is already defined at 0x{:08X}",
+                name, offset, previous
+            ),
+            LoadError::UndefinedLabel { name, offset } => {
+                write!(f, "label '{}' not found at 0x{:08X}", name, offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<DecodeError> for LoadError {
+    fn from(err: DecodeError) -> Self {
+        LoadError::Decode(err)
+    }
+}
@@ -0,0 +1,142 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    io::{self, BufRead, Write},
+    rc::Rc,
+};
+
+pub trait IoHost {
+    fn read_line(&mut self) -> io::Result<String>;
+    fn write_stdout(&mut self, bytes: &[u8]) -> io::Result<()>;
+    fn write_stderr(&mut self, bytes: &[u8]) -> io::Result<()>;
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct Streams<R, O, E> {
+    stdin: R,
+    stdout: O,
+    stderr: E,
+}
+
+impl<R: BufRead, O: Write, E: Write> Streams<R, O, E> {
+    pub fn new(stdin: R, stdout: O, stderr: E) -> Self {
+        Self {
+            stdin,
+            stdout,
+            stderr,
+        }
+    }
+}
+
+impl<R: BufRead, O: Write, E: Write> IoHost for Streams<R, O, E> {
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        self.stdin.read_line(&mut line)?;
+        Ok(line)
+    }
+
+    fn write_stdout(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.stdout.write_all(bytes)
+    }
+
+    fn write_stderr(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.stderr.write_all(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()?;
+        self.stderr.flush()
+    }
+}
+
+/// The process streams, any of which can be replaced. Stdin is only locked
+/// while a line is read, so the host can still use it between `INPUT`s.
+#[derive(Default)]
+pub(crate) struct ProcessIo {
+    pub(crate) stdin: Option<Box<dyn BufRead>>,
+    pub(crate) stdout: Option<Box<dyn Write>>,
+    pub(crate) stderr: Option<Box<dyn Write>>,
+}
+
+impl IoHost for ProcessIo {
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        match &mut self.stdin {
+            Some(stdin) => stdin.read_line(&mut line)?,
+            None => io::stdin().read_line(&mut line)?,
+        };
+        Ok(line)
+    }
+
+    fn write_stdout(&mut self, bytes: &[u8]) -> io::Result<()> {
+        match &mut self.stdout {
+            Some(stdout) => stdout.write_all(bytes),
+            None => io::stdout().write_all(bytes),
+        }
+    }
+
+    fn write_stderr(&mut self, bytes: &[u8]) -> io::Result<()> {
+        match &mut self.stderr {
+            Some(stderr) => stderr.write_all(bytes),
+            None => io::stderr().write_all(bytes),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.stdout {
+            Some(stdout) => stdout.flush()?,
+            None => io::stdout().flush()?,
+        }
+        match &mut self.stderr {
+            Some(stderr) => stderr.flush(),
+            None => io::stderr().flush(),
+        }
+    }
+}
+
+/// An in-memory host that serves scripted input lines and captures output.
+/// Clones share the same buffers, so a clone kept by the caller can inspect
+/// what the VM wrote after the run.
+#[derive(Clone, Default)]
+pub struct BufferIo {
+    input: Rc<RefCell<VecDeque<String>>>,
+    stdout: Rc<RefCell<Vec<u8>>>,
+    stderr: Rc<RefCell<Vec<u8>>>,
+}
+
+impl BufferIo {
+    pub fn new(input: &str) -> Self {
+        let io = Self::default();
+        io.input
+            .borrow_mut()
+            .extend(input.split_inclusive('\n').map(String::from));
+        io
+    }
+
+    pub fn stdout(&self) -> String {
+        String::from_utf8_lossy(&self.stdout.borrow()).to_string()
+    }
+
+    pub fn stderr(&self) -> String {
+        String::from_utf8_lossy(&self.stderr.borrow()).to_string()
+    }
+}
+
+impl IoHost for BufferIo {
+    fn read_line(&mut self) -> io::Result<String> {
+        Ok(self.input.borrow_mut().pop_front().unwrap_or_default())
+    }
+
+    fn write_stdout(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.stdout.borrow_mut().extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn write_stderr(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.stderr.borrow_mut().extend_from_slice(bytes);
+        Ok(())
+    }
+}
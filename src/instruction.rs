@@ -0,0 +1,644 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    bigint::{BigInt, MAX_BITS},
+    error::ErrorKind,
+    opcode::*,
+};
+
+/// A jump or call destination. `index` is the position of the target `LABEL`
+/// in the decoded instruction list, filled in by `decode` once every label is
+/// known, and left as `None` when no such label exists.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub label: String,
+    pub index: Option<usize>,
+}
+
+impl Target {
+    pub fn new(label: String) -> Self {
+        Self { label, index: None }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Debug,
+    PushInteger { value: i64 },
+    PushFloat { value: f64 },
+    PushString { value: String },
+    PushBoolean { value: bool },
+    PushIntegerPower { power: u8 },
+    PushIntegerPowerSub { power: u8 },
+    PushNull,
+    PushBigInteger { value: BigInt },
+    Load { name: String },
+    Store { name: String },
+    Dup,
+    Swap,
+    Pop,
+    Free { name: String },
+    LoadRef,
+    StoreRef,
+    LoadGlobal { name: String },
+    StoreGlobal { name: String },
+    LoadOrNull { name: String },
+    IsNull,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    AddWrapping,
+    SubWrapping,
+    MulWrapping,
+    AddSaturating,
+    SubSaturating,
+    MulSaturating,
+    StrGetSlice,
+    StrLength,
+    StrFind,
+    StrContains,
+    StrStartsWith,
+    StrEndsWith,
+    StrReplace,
+    StrSplit,
+    StrJoin,
+    StrUpper,
+    StrLower,
+    StrTrim,
+    StrRepeat,
+    Cast { cast_type: u8 },
+    ListNew,
+    ListPush,
+    ListPop,
+    ListGet,
+    ListSet,
+    ListLength,
+    ListSlice,
+    MapNew,
+    MapGet,
+    MapSet,
+    MapDelete,
+    MapContains,
+    MapKeys,
+    And,
+    Or,
+    Xor,
+    Not,
+    Shl,
+    Shr,
+    Math { math_type: u8 },
+    Cmp { cmp_type: u8 },
+    Label { name: String },
+    Jump { target: Target },
+    JumpIfTrue { target: Target },
+    JumpIfFalse { target: Target },
+    Call { target: Target },
+    Return,
+    Func { name: String, args: u8, returns: u8 },
+    DisplayStdout,
+    DisplayStderr,
+    Input,
+    Exit,
+}
+
+#[derive(Debug)]
+pub struct DecodeError {
+    /// The opcode being decoded, or `None` if the offset was past the end.
+    pub opcode: Option<u8>,
+    pub offset: usize,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at 0x{:08X}", self.kind, self.offset)?;
+        match self.opcode {
+            Some(opcode) => write!(f, " (0x{:02X} {})", opcode, opcode_name(opcode)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+struct Reader<'a> {
+    bytecode: &'a [u8],
+    index: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], ErrorKind> {
+        if self.index + count > self.bytecode.len() {
+            return Err(ErrorKind::Truncated);
+        }
+
+        let bytes = &self.bytecode[self.index..self.index + count];
+        self.index += count;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, ErrorKind> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], ErrorKind> {
+        Ok(self.read_bytes(N)?.try_into().unwrap())
+    }
+
+    fn read_name(&mut self) -> Result<String, ErrorKind> {
+        let str_len = self.read_u8()? as usize;
+        let name_bytes = self.read_bytes(str_len)?;
+        Ok(String::from_utf8_lossy(name_bytes).to_string())
+    }
+
+    fn read_instruction(&mut self, opcode: u8) -> Result<Instruction, ErrorKind> {
+        let instruction = match opcode {
+            OP_DEBUG => Instruction::Debug,
+            OP_PUSH => {
+                let datatype = self.read_u8()?;
+
+                match datatype {
+                    PUSH_TYPE_INTEGER => Instruction::PushInteger {
+                        value: i64::from_le_bytes(self.read_array()?),
+                    },
+                    PUSH_TYPE_FLOAT => Instruction::PushFloat {
+                        value: f64::from_le_bytes(self.read_array()?),
+                    },
+                    PUSH_TYPE_STRING => {
+                        let str_len = u32::from_le_bytes(self.read_array()?) as usize;
+                        let str_bytes = self.read_bytes(str_len)?;
+                        Instruction::PushString {
+                            value: String::from_utf8_lossy(str_bytes).to_string(),
+                        }
+                    }
+                    PUSH_TYPE_BOOLEAN => match self.read_u8()? {
+                        0x00 => Instruction::PushBoolean { value: false },
+                        0x01 => Instruction::PushBoolean { value: true },
+                        bool_byte => return Err(ErrorKind::InvalidBoolean(bool_byte)),
+                    },
+                    PUSH_TYPE_INTEGER_POWER => Instruction::PushIntegerPower {
+                        power: self.read_u8()?,
+                    },
+                    PUSH_TYPE_INTEGER_POWER_SUB => Instruction::PushIntegerPowerSub {
+                        power: self.read_u8()?,
+                    },
+                    PUSH_TYPE_NULL => Instruction::PushNull,
+                    PUSH_TYPE_BIG_INTEGER => {
+                        let len = u32::from_le_bytes(self.read_array()?) as usize;
+                        let value = BigInt::from_signed_bytes_le(self.read_bytes(len)?);
+                        if value.bits() > MAX_BITS {
+                            return Err(ErrorKind::BigIntegerTooLarge);
+                        }
+                        Instruction::PushBigInteger { value }
+                    }
+                    _ => return Err(ErrorKind::UnknownPushType(datatype)),
+                }
+            }
+            OP_LOAD => Instruction::Load {
+                name: self.read_name()?,
+            },
+            OP_STORE => Instruction::Store {
+                name: self.read_name()?,
+            },
+            OP_DUP => Instruction::Dup,
+            OP_SWAP => Instruction::Swap,
+            OP_POP => Instruction::Pop,
+            OP_FREE => Instruction::Free {
+                name: self.read_name()?,
+            },
+            OP_LOAD_REF => Instruction::LoadRef,
+            OP_STORE_REF => Instruction::StoreRef,
+            OP_LOAD_GLOBAL => Instruction::LoadGlobal {
+                name: self.read_name()?,
+            },
+            OP_STORE_GLOBAL => Instruction::StoreGlobal {
+                name: self.read_name()?,
+            },
+            OP_LOAD_OR_NULL => Instruction::LoadOrNull {
+                name: self.read_name()?,
+            },
+            OP_IS_NULL => Instruction::IsNull,
+            OP_ADD => Instruction::Add,
+            OP_SUB => Instruction::Sub,
+            OP_MUL => Instruction::Mul,
+            OP_DIV => Instruction::Div,
+            OP_MOD => Instruction::Mod,
+            OP_ADD_WRAPPING => Instruction::AddWrapping,
+            OP_SUB_WRAPPING => Instruction::SubWrapping,
+            OP_MUL_WRAPPING => Instruction::MulWrapping,
+            OP_ADD_SATURATING => Instruction::AddSaturating,
+            OP_SUB_SATURATING => Instruction::SubSaturating,
+            OP_MUL_SATURATING => Instruction::MulSaturating,
+            OP_STR_GET_SLICE => Instruction::StrGetSlice,
+            OP_STR_LENGTH => Instruction::StrLength,
+            OP_STR_FIND => Instruction::StrFind,
+            OP_STR_CONTAINS => Instruction::StrContains,
+            OP_STR_STARTS_WITH => Instruction::StrStartsWith,
+            OP_STR_ENDS_WITH => Instruction::StrEndsWith,
+            OP_STR_REPLACE => Instruction::StrReplace,
+            OP_STR_SPLIT => Instruction::StrSplit,
+            OP_STR_JOIN => Instruction::StrJoin,
+            OP_STR_UPPER => Instruction::StrUpper,
+            OP_STR_LOWER => Instruction::StrLower,
+            OP_STR_TRIM => Instruction::StrTrim,
+            OP_STR_REPEAT => Instruction::StrRepeat,
+            OP_CAST => Instruction::Cast {
+                cast_type: self.read_u8()?,
+            },
+            OP_LIST_NEW => Instruction::ListNew,
+            OP_LIST_PUSH => Instruction::ListPush,
+            OP_LIST_POP => Instruction::ListPop,
+            OP_LIST_GET => Instruction::ListGet,
+            OP_LIST_SET => Instruction::ListSet,
+            OP_LIST_LENGTH => Instruction::ListLength,
+            OP_LIST_SLICE => Instruction::ListSlice,
+            OP_MAP_NEW => Instruction::MapNew,
+            OP_MAP_GET => Instruction::MapGet,
+            OP_MAP_SET => Instruction::MapSet,
+            OP_MAP_DELETE => Instruction::MapDelete,
+            OP_MAP_CONTAINS => Instruction::MapContains,
+            OP_MAP_KEYS => Instruction::MapKeys,
+            OP_AND => Instruction::And,
+            OP_OR => Instruction::Or,
+            OP_XOR => Instruction::Xor,
+            OP_NOT => Instruction::Not,
+            OP_SHL => Instruction::Shl,
+            OP_SHR => Instruction::Shr,
+            OP_MATH => Instruction::Math {
+                math_type: self.read_u8()?,
+            },
+            OP_CMP => Instruction::Cmp {
+                cmp_type: self.read_u8()?,
+            },
+            OP_LABEL => Instruction::Label {
+                name: self.read_name()?,
+            },
+            OP_JUMP => Instruction::Jump {
+                target: Target::new(self.read_name()?),
+            },
+            OP_JUMP_IF_TRUE => Instruction::JumpIfTrue {
+                target: Target::new(self.read_name()?),
+            },
+            OP_JUMP_IF_FALSE => Instruction::JumpIfFalse {
+                target: Target::new(self.read_name()?),
+            },
+            OP_CALL => Instruction::Call {
+                target: Target::new(self.read_name()?),
+            },
+            OP_RETURN => Instruction::Return,
+            OP_FUNC => Instruction::Func {
+                name: self.read_name()?,
+                args: self.read_u8()?,
+                returns: self.read_u8()?,
+            },
+            OP_DISPLAY_STDOUT => Instruction::DisplayStdout,
+            OP_DISPLAY_STDERR => Instruction::DisplayStderr,
+            OP_INPUT => Instruction::Input,
+            OP_EXIT => Instruction::Exit,
+            _ => return Err(ErrorKind::UnknownOpcode),
+        };
+
+        Ok(instruction)
+    }
+}
+
+/// Decodes the instruction starting at `offset`, returning it together with
+/// the offset of the instruction that follows.
+pub fn decode_at(bytecode: &[u8], offset: usize) -> Result<(Instruction, usize), DecodeError> {
+    let Some(&opcode) = bytecode.get(offset) else {
+        return Err(DecodeError {
+            opcode: None,
+            offset,
+            kind: ErrorKind::Truncated,
+        });
+    };
+
+    let mut reader = Reader {
+        bytecode,
+        index: offset + 1,
+    };
+
+    match reader.read_instruction(opcode) {
+        Ok(instruction) => Ok((instruction, reader.index)),
+        Err(kind) => Err(DecodeError {
+            opcode: Some(opcode),
+            offset,
+            kind,
+        }),
+    }
+}
+
+/// Decodes a whole program into `(offset, instruction)` pairs and resolves
+/// every jump and call to the index of its `LABEL`. When a label is defined
+/// more than once, the last definition wins.
+pub fn decode(bytecode: &[u8]) -> Result<Vec<(usize, Instruction)>, DecodeError> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+
+    while offset < bytecode.len() {
+        let (instruction, next) = decode_at(bytecode, offset)?;
+        instructions.push((offset, instruction));
+        offset = next;
+    }
+
+    let labels = instructions
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (_, instruction))| {
+            instruction
+                .label_name()
+                .map(|name| (name.to_string(), index))
+        })
+        .collect::<HashMap<String, usize>>();
+
+    for (_, instruction) in &mut instructions {
+        if let Some(target) = instruction.target_mut() {
+            target.index = labels.get(&target.label).copied();
+        }
+    }
+
+    Ok(instructions)
+}
+
+fn encode_name(out: &mut Vec<u8>, name: &str) {
+    out.push(name.len() as u8);
+    out.extend_from_slice(name.as_bytes());
+}
+
+impl Instruction {
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Instruction::Debug => OP_DEBUG,
+            Instruction::PushInteger { .. }
+            | Instruction::PushFloat { .. }
+            | Instruction::PushString { .. }
+            | Instruction::PushBoolean { .. }
+            | Instruction::PushIntegerPower { .. }
+            | Instruction::PushIntegerPowerSub { .. }
+            | Instruction::PushNull
+            | Instruction::PushBigInteger { .. } => OP_PUSH,
+            Instruction::Load { .. } => OP_LOAD,
+            Instruction::Store { .. } => OP_STORE,
+            Instruction::Dup => OP_DUP,
+            Instruction::Swap => OP_SWAP,
+            Instruction::Pop => OP_POP,
+            Instruction::Free { .. } => OP_FREE,
+            Instruction::LoadRef => OP_LOAD_REF,
+            Instruction::StoreRef => OP_STORE_REF,
+            Instruction::LoadGlobal { .. } => OP_LOAD_GLOBAL,
+            Instruction::StoreGlobal { .. } => OP_STORE_GLOBAL,
+            Instruction::LoadOrNull { .. } => OP_LOAD_OR_NULL,
+            Instruction::IsNull => OP_IS_NULL,
+            Instruction::Add => OP_ADD,
+            Instruction::Sub => OP_SUB,
+            Instruction::Mul => OP_MUL,
+            Instruction::Div => OP_DIV,
+            Instruction::Mod => OP_MOD,
+            Instruction::AddWrapping => OP_ADD_WRAPPING,
+            Instruction::SubWrapping => OP_SUB_WRAPPING,
+            Instruction::MulWrapping => OP_MUL_WRAPPING,
+            Instruction::AddSaturating => OP_ADD_SATURATING,
+            Instruction::SubSaturating => OP_SUB_SATURATING,
+            Instruction::MulSaturating => OP_MUL_SATURATING,
+            Instruction::StrGetSlice => OP_STR_GET_SLICE,
+            Instruction::StrLength => OP_STR_LENGTH,
+            Instruction::StrFind => OP_STR_FIND,
+            Instruction::StrContains => OP_STR_CONTAINS,
+            Instruction::StrStartsWith => OP_STR_STARTS_WITH,
+            Instruction::StrEndsWith => OP_STR_ENDS_WITH,
+            Instruction::StrReplace => OP_STR_REPLACE,
+            Instruction::StrSplit => OP_STR_SPLIT,
+            Instruction::StrJoin => OP_STR_JOIN,
+            Instruction::StrUpper => OP_STR_UPPER,
+            Instruction::StrLower => OP_STR_LOWER,
+            Instruction::StrTrim => OP_STR_TRIM,
+            Instruction::StrRepeat => OP_STR_REPEAT,
+            Instruction::Cast { .. } => OP_CAST,
+            Instruction::ListNew => OP_LIST_NEW,
+            Instruction::ListPush => OP_LIST_PUSH,
+            Instruction::ListPop => OP_LIST_POP,
+            Instruction::ListGet => OP_LIST_GET,
+            Instruction::ListSet => OP_LIST_SET,
+            Instruction::ListLength => OP_LIST_LENGTH,
+            Instruction::ListSlice => OP_LIST_SLICE,
+            Instruction::MapNew => OP_MAP_NEW,
+            Instruction::MapGet => OP_MAP_GET,
+            Instruction::MapSet => OP_MAP_SET,
+            Instruction::MapDelete => OP_MAP_DELETE,
+            Instruction::MapContains => OP_MAP_CONTAINS,
+            Instruction::MapKeys => OP_MAP_KEYS,
+            Instruction::And => OP_AND,
+            Instruction::Or => OP_OR,
+            Instruction::Xor => OP_XOR,
+            Instruction::Not => OP_NOT,
+            Instruction::Shl => OP_SHL,
+            Instruction::Shr => OP_SHR,
+            Instruction::Math { .. } => OP_MATH,
+            Instruction::Cmp { .. } => OP_CMP,
+            Instruction::Label { .. } => OP_LABEL,
+            Instruction::Jump { .. } => OP_JUMP,
+            Instruction::JumpIfTrue { .. } => OP_JUMP_IF_TRUE,
+            Instruction::JumpIfFalse { .. } => OP_JUMP_IF_FALSE,
+            Instruction::Call { .. } => OP_CALL,
+            Instruction::Return => OP_RETURN,
+            Instruction::Func { .. } => OP_FUNC,
+            Instruction::DisplayStdout => OP_DISPLAY_STDOUT,
+            Instruction::DisplayStderr => OP_DISPLAY_STDERR,
+            Instruction::Input => OP_INPUT,
+            Instruction::Exit => OP_EXIT,
+        }
+    }
+
+    /// The name this instruction defines as a jump or call destination.
+    pub fn label_name(&self) -> Option<&str> {
+        match self {
+            Instruction::Label { name } | Instruction::Func { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    pub fn target(&self) -> Option<&Target> {
+        match self {
+            Instruction::Jump { target }
+            | Instruction::JumpIfTrue { target }
+            | Instruction::JumpIfFalse { target }
+            | Instruction::Call { target } => Some(target),
+            _ => None,
+        }
+    }
+
+    fn target_mut(&mut self) -> Option<&mut Target> {
+        match self {
+            Instruction::Jump { target }
+            | Instruction::JumpIfTrue { target }
+            | Instruction::JumpIfFalse { target }
+            | Instruction::Call { target } => Some(target),
+            _ => None,
+        }
+    }
+
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        out.push(self.opcode());
+
+        match self {
+            Instruction::PushInteger { value } => {
+                out.push(PUSH_TYPE_INTEGER);
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            Instruction::PushFloat { value } => {
+                out.push(PUSH_TYPE_FLOAT);
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            Instruction::PushString { value } => {
+                out.push(PUSH_TYPE_STRING);
+                out.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                out.extend_from_slice(value.as_bytes());
+            }
+            Instruction::PushBoolean { value } => {
+                out.push(PUSH_TYPE_BOOLEAN);
+                out.push(*value as u8);
+            }
+            Instruction::PushIntegerPower { power } => {
+                out.push(PUSH_TYPE_INTEGER_POWER);
+                out.push(*power);
+            }
+            Instruction::PushIntegerPowerSub { power } => {
+                out.push(PUSH_TYPE_INTEGER_POWER_SUB);
+                out.push(*power);
+            }
+            Instruction::PushNull => out.push(PUSH_TYPE_NULL),
+            Instruction::PushBigInteger { value } => {
+                let bytes = value.to_signed_bytes_le();
+                out.push(PUSH_TYPE_BIG_INTEGER);
+                out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+                out.extend_from_slice(&bytes);
+            }
+            Instruction::Load { name }
+            | Instruction::Store { name }
+            | Instruction::Free { name }
+            | Instruction::LoadGlobal { name }
+            | Instruction::StoreGlobal { name }
+            | Instruction::LoadOrNull { name }
+            | Instruction::Label { name } => encode_name(out, name),
+            Instruction::Jump { target }
+            | Instruction::JumpIfTrue { target }
+            | Instruction::JumpIfFalse { target }
+            | Instruction::Call { target } => encode_name(out, &target.label),
+            Instruction::Func {
+                name,
+                args,
+                returns,
+            } => {
+                encode_name(out, name);
+                out.push(*args);
+                out.push(*returns);
+            }
+            Instruction::Cast { cast_type } => out.push(*cast_type),
+            Instruction::Math { math_type } => out.push(*math_type),
+            Instruction::Cmp { cmp_type } => out.push(*cmp_type),
+            _ => {}
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in value.chars() {
+        match c {
+            '\n' => write!(f, "\\n")?,
+            '\t' => write!(f, "\\t")?,
+            '\r' => write!(f, "\\r")?,
+            '\0' => write!(f, "\\0")?,
+            '\\' => write!(f, "\\\\")?,
+            '"' => write!(f, "\\\"")?,
+            c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+fn write_name(f: &mut fmt::Formatter<'_>, name: &str) -> fmt::Result {
+    let bare = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| !c.is_whitespace() && !c.is_control() && c != ';' && c != '"');
+
+    if bare {
+        write!(f, "{}", name)
+    } else {
+        write_string(f, name)
+    }
+}
+
+fn write_sub_type(
+    f: &mut fmt::Formatter<'_>,
+    table: &[(u8, &'static str)],
+    code: u8,
+) -> fmt::Result {
+    match name_of(table, code) {
+        Some(name) => write!(f, "{}", name),
+        None => write!(f, "0x{:02X}", code),
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", opcode_name(self.opcode()))?;
+
+        match self {
+            Instruction::PushInteger { value } => write!(f, " INT {}", value),
+            Instruction::PushFloat { value } => write!(f, " FLOAT {:?}", value),
+            Instruction::PushString { value } => {
+                write!(f, " STRING ")?;
+                write_string(f, value)
+            }
+            Instruction::PushBoolean { value } => write!(f, " BOOL {}", value),
+            Instruction::PushIntegerPower { power } => write!(f, " INT_POW {}", power),
+            Instruction::PushIntegerPowerSub { power } => write!(f, " INT_POW_SUB {}", power),
+            Instruction::PushNull => write!(f, " NULL"),
+            Instruction::PushBigInteger { value } => write!(f, " BIGINT {}", value),
+            Instruction::Load { name }
+            | Instruction::Store { name }
+            | Instruction::Free { name }
+            | Instruction::LoadGlobal { name }
+            | Instruction::StoreGlobal { name }
+            | Instruction::LoadOrNull { name }
+            | Instruction::Label { name } => {
+                write!(f, " ")?;
+                write_name(f, name)
+            }
+            Instruction::Jump { target }
+            | Instruction::JumpIfTrue { target }
+            | Instruction::JumpIfFalse { target }
+            | Instruction::Call { target } => {
+                write!(f, " ")?;
+                write_name(f, &target.label)
+            }
+            Instruction::Func {
+                name,
+                args,
+                returns,
+            } => {
+                write!(f, " ")?;
+                write_name(f, name)?;
+                write!(f, " {} {}", args, returns)
+            }
+            Instruction::Cast { cast_type } => {
+                write!(f, " ")?;
+                write_sub_type(f, CAST_TYPES, *cast_type)
+            }
+            Instruction::Math { math_type } => {
+                write!(f, " ")?;
+                write_sub_type(f, MATH_TYPES, *math_type)
+            }
+            Instruction::Cmp { cmp_type } => {
+                write!(f, " ")?;
+                write_sub_type(f, CMP_TYPES, *cmp_type)
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,19 @@
+mod asm;
+mod bigint;
+mod dis;
+mod error;
+mod host;
+mod instruction;
+pub mod opcode;
+mod parse;
+mod program;
+mod vm;
+
+pub use asm::{AsmError, assemble};
+pub use bigint::{BigInt, ParseBigIntError};
+pub use dis::disassemble;
+pub use error::{ErrorKind, LoadError, VmError};
+pub use host::{BufferIo, IoHost, Streams};
+pub use instruction::{DecodeError, Instruction, Target, decode, decode_at};
+pub use program::Program;
+pub use vm::{ExitStatus, Frame, IVMType, Permissions, VM, VMBuilder};
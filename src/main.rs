@@ -1,36 +1,81 @@
-use std::env::args;
+use std::{env::args, path::Path};
 
-mod vm;
+use ivm::{ExitStatus, VM};
+
+mod debugger;
+
+const USAGE: &str = "Usage:
+    ivm [run] <file.ivm>
+    ivm asm <file.ivma> [-o <file.ivm>]
+    ivm dis <file.ivm>
+    ivm debug <file.ivm>";
+
+fn run(args: &[String]) -> Result<(), String> {
+    let [filename] = args else {
+        return Err(USAGE.to_string());
+    };
+
+    let data =
+        std::fs::read(filename).map_err(|e| format!("Failed to read {}: {}", filename, e))?;
+
+    let program = VM::load(&data).map_err(|e| e.to_string())?;
+    let mut vm = VM::new(program);
+
+    match vm.run().map_err(|e| e.to_string())? {
+        ExitStatus::Exited(code) => {
+            drop(vm);
+            std::process::exit(code)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn asm(args: &[String]) -> Result<(), String> {
+    let (input, output) = match args {
+        [input] => (input, Path::new(input).with_extension("ivm")),
+        [input, flag, output] if flag == "-o" => (input, output.into()),
+        _ => return Err(USAGE.to_string()),
+    };
+
+    if Path::new(input) == output {
+        return Err(format!("Refusing to overwrite {}, pass -o", input));
+    }
+
+    let source =
+        std::fs::read_to_string(input).map_err(|e| format!("Failed to read {}: {}", input, e))?;
+    let bytecode = ivm::assemble(&source).map_err(|e| format!("{}:{}", input, e))?;
+
+    std::fs::write(&output, bytecode)
+        .map_err(|e| format!("Failed to write {}: {}", output.display(), e))
+}
+
+fn dis(args: &[String]) -> Result<(), String> {
+    let [filename] = args else {
+        return Err(USAGE.to_string());
+    };
+
+    let data =
+        std::fs::read(filename).map_err(|e| format!("Failed to read {}: {}", filename, e))?;
+    let listing = ivm::disassemble(&data).map_err(|e| e.to_string())?;
+
+    print!("{}", listing);
+    Ok(())
+}
 
 fn main() {
-    // let data = vec![
-    //     0x01, 0x06, 0x02, // PUSH_TYPE_INTEGER_POWER_SUB, power 2
-    //     0x04,             // DUP
-    //     0x12,             // MUL
-    //     0x03, 0x01, 0x41, // STORE 'A'
-    //     0x02, 0x01, 0x41, // LOAD 'A'
-    //     0x00,             // DEBUG
-    // ];
-    // let data = vec![
-    //     0x01, 0x05, 0x02, // PUSH_INTEGER_POWER, power 2
-    //     0x04,             // DUP
-    //     0x12,             // MUL
-    //     0xE0, 0x01, 0x41, // LABEL 'A'
-    //     0x04,             // DUP
-    //     0xF0,             // DISPLAY_STDOUT
-    //     0x01, 0x06, 0x01, // PUSH_INTEGER_POWER_SUB, power 1
-    //     0x11,             // SUB
-    //     0x04,             // DUP
-    //     0x01, 0x06, 0x00, // PUSH_INTEGER_POWER_SUB, power 0
-    //     0xD0, 0x02,       // CMP (not equal)
-    //     0xE2, 0x01, 0x41, // JUMP (true) 'A'
-    // ];
-
-    let filename = args().nth(1).expect("No input file specified");
-    let data = std::fs::read(filename).expect("Failed to read input file");
-
-    let mut vm = vm::VM::new(data);
-
-    vm.run(true);
-    vm.run(false);
+    let args = args().skip(1).collect::<Vec<String>>();
+
+    let result = match args.first().map(String::as_str) {
+        Some("run") => run(&args[1..]),
+        Some("asm") => asm(&args[1..]),
+        Some("dis") => dis(&args[1..]),
+        Some("debug") => debugger::debug(&args[1..]),
+        Some(_) => run(&args),
+        None => Err(USAGE.to_string()),
+    };
+
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,273 @@
+pub const OP_DEBUG: u8 = 0x00;
+pub const OP_PUSH: u8 = 0x01;
+pub const OP_LOAD: u8 = 0x02;
+pub const OP_STORE: u8 = 0x03;
+pub const OP_DUP: u8 = 0x04;
+pub const OP_SWAP: u8 = 0x05;
+pub const OP_POP: u8 = 0x06;
+pub const OP_FREE: u8 = 0x07;
+pub const OP_LOAD_REF: u8 = 0x08;
+pub const OP_STORE_REF: u8 = 0x09;
+pub const OP_LOAD_GLOBAL: u8 = 0x0A;
+pub const OP_STORE_GLOBAL: u8 = 0x0B;
+pub const OP_LOAD_OR_NULL: u8 = 0x0C;
+pub const OP_IS_NULL: u8 = 0x0D;
+
+pub const OP_ADD: u8 = 0x10;
+pub const OP_SUB: u8 = 0x11;
+pub const OP_MUL: u8 = 0x12;
+pub const OP_DIV: u8 = 0x13;
+pub const OP_MOD: u8 = 0x14;
+pub const OP_ADD_WRAPPING: u8 = 0x15;
+pub const OP_SUB_WRAPPING: u8 = 0x16;
+pub const OP_MUL_WRAPPING: u8 = 0x17;
+pub const OP_ADD_SATURATING: u8 = 0x18;
+pub const OP_SUB_SATURATING: u8 = 0x19;
+pub const OP_MUL_SATURATING: u8 = 0x1A;
+
+pub const OP_STR_GET_SLICE: u8 = 0x20;
+pub const OP_STR_LENGTH: u8 = 0x21;
+pub const OP_STR_FIND: u8 = 0x22;
+pub const OP_STR_CONTAINS: u8 = 0x23;
+pub const OP_STR_STARTS_WITH: u8 = 0x24;
+pub const OP_STR_ENDS_WITH: u8 = 0x25;
+pub const OP_STR_REPLACE: u8 = 0x26;
+pub const OP_STR_SPLIT: u8 = 0x27;
+pub const OP_STR_JOIN: u8 = 0x28;
+pub const OP_STR_UPPER: u8 = 0x29;
+pub const OP_STR_LOWER: u8 = 0x2A;
+pub const OP_STR_TRIM: u8 = 0x2B;
+pub const OP_STR_REPEAT: u8 = 0x2C;
+
+pub const OP_CAST: u8 = 0x30;
+
+pub const OP_LIST_NEW: u8 = 0x40;
+pub const OP_LIST_PUSH: u8 = 0x41;
+pub const OP_LIST_POP: u8 = 0x42;
+pub const OP_LIST_GET: u8 = 0x43;
+pub const OP_LIST_SET: u8 = 0x44;
+pub const OP_LIST_LENGTH: u8 = 0x45;
+pub const OP_LIST_SLICE: u8 = 0x46;
+
+pub const OP_MAP_NEW: u8 = 0x50;
+pub const OP_MAP_GET: u8 = 0x51;
+pub const OP_MAP_SET: u8 = 0x52;
+pub const OP_MAP_DELETE: u8 = 0x53;
+pub const OP_MAP_CONTAINS: u8 = 0x54;
+pub const OP_MAP_KEYS: u8 = 0x55;
+
+pub const OP_AND: u8 = 0x60;
+pub const OP_OR: u8 = 0x61;
+pub const OP_XOR: u8 = 0x62;
+pub const OP_NOT: u8 = 0x63;
+pub const OP_SHL: u8 = 0x64;
+pub const OP_SHR: u8 = 0x65;
+
+pub const OP_MATH: u8 = 0x70;
+
+pub const OP_CMP: u8 = 0xD0;
+
+pub const OP_LABEL: u8 = 0xE0;
+pub const OP_JUMP: u8 = 0xE1;
+pub const OP_JUMP_IF_TRUE: u8 = 0xE2;
+pub const OP_JUMP_IF_FALSE: u8 = 0xE3;
+pub const OP_CALL: u8 = 0xE4;
+pub const OP_RETURN: u8 = 0xE5;
+pub const OP_FUNC: u8 = 0xE6;
+
+pub const OP_DISPLAY_STDOUT: u8 = 0xF0;
+pub const OP_DISPLAY_STDERR: u8 = 0xF1;
+pub const OP_INPUT: u8 = 0xF2;
+pub const OP_EXIT: u8 = 0xFF;
+
+pub const PUSH_TYPE_INTEGER: u8 = 0x01;
+pub const PUSH_TYPE_FLOAT: u8 = 0x02;
+pub const PUSH_TYPE_STRING: u8 = 0x03;
+pub const PUSH_TYPE_BOOLEAN: u8 = 0x04;
+pub const PUSH_TYPE_INTEGER_POWER: u8 = 0x05;
+pub const PUSH_TYPE_INTEGER_POWER_SUB: u8 = 0x06;
+pub const PUSH_TYPE_NULL: u8 = 0x07;
+pub const PUSH_TYPE_BIG_INTEGER: u8 = 0x08;
+
+pub const CAST_TYPE_ITOS: u8 = 0x01;
+pub const CAST_TYPE_STOI: u8 = 0x02;
+pub const CAST_TYPE_ITOBIG: u8 = 0x03;
+pub const CAST_TYPE_BIGTOI: u8 = 0x04;
+pub const CAST_TYPE_ITOF: u8 = 0x05;
+pub const CAST_TYPE_FTOI: u8 = 0x06;
+pub const CAST_TYPE_FTOI_ROUND: u8 = 0x07;
+pub const CAST_TYPE_FTOI_FLOOR: u8 = 0x08;
+pub const CAST_TYPE_FTOI_CEIL: u8 = 0x09;
+pub const CAST_TYPE_BOOLTOI: u8 = 0x0A;
+pub const CAST_TYPE_ITOBOOL: u8 = 0x0B;
+pub const CAST_TYPE_FORMAT: u8 = 0x0C;
+pub const CAST_TYPE_PARSE_INT: u8 = 0x0D;
+pub const CAST_TYPE_PARSE_FLOAT: u8 = 0x0E;
+
+pub const CMP_TYPE_EQUAL: u8 = 0x01;
+pub const CMP_TYPE_NOT_EQUAL: u8 = 0x02;
+pub const CMP_TYPE_LESS_THAN: u8 = 0x03;
+pub const CMP_TYPE_GREATER_THAN: u8 = 0x04;
+pub const CMP_TYPE_LESS_EQUAL: u8 = 0x05;
+pub const CMP_TYPE_GREATER_EQUAL: u8 = 0x06;
+
+pub const MATH_TYPE_SQRT: u8 = 0x01;
+pub const MATH_TYPE_POW: u8 = 0x02;
+pub const MATH_TYPE_ABS: u8 = 0x03;
+pub const MATH_TYPE_FLOOR: u8 = 0x04;
+pub const MATH_TYPE_CEIL: u8 = 0x05;
+pub const MATH_TYPE_ROUND: u8 = 0x06;
+pub const MATH_TYPE_SIN: u8 = 0x07;
+pub const MATH_TYPE_COS: u8 = 0x08;
+pub const MATH_TYPE_TAN: u8 = 0x09;
+pub const MATH_TYPE_ATAN2: u8 = 0x0A;
+pub const MATH_TYPE_EXP: u8 = 0x0B;
+pub const MATH_TYPE_LN: u8 = 0x0C;
+pub const MATH_TYPE_MIN: u8 = 0x0D;
+pub const MATH_TYPE_MAX: u8 = 0x0E;
+
+pub const OPCODES: &[(u8, &str)] = &[
+    (OP_DEBUG, "DEBUG"),
+    (OP_PUSH, "PUSH"),
+    (OP_LOAD, "LOAD"),
+    (OP_STORE, "STORE"),
+    (OP_DUP, "DUP"),
+    (OP_SWAP, "SWAP"),
+    (OP_POP, "POP"),
+    (OP_FREE, "FREE"),
+    (OP_LOAD_REF, "LOAD_REF"),
+    (OP_STORE_REF, "STORE_REF"),
+    (OP_LOAD_GLOBAL, "LOAD_GLOBAL"),
+    (OP_STORE_GLOBAL, "STORE_GLOBAL"),
+    (OP_LOAD_OR_NULL, "LOAD_OR_NULL"),
+    (OP_IS_NULL, "IS_NULL"),
+    (OP_ADD, "ADD"),
+    (OP_SUB, "SUB"),
+    (OP_MUL, "MUL"),
+    (OP_DIV, "DIV"),
+    (OP_MOD, "MOD"),
+    (OP_ADD_WRAPPING, "ADD_WRAPPING"),
+    (OP_SUB_WRAPPING, "SUB_WRAPPING"),
+    (OP_MUL_WRAPPING, "MUL_WRAPPING"),
+    (OP_ADD_SATURATING, "ADD_SATURATING"),
+    (OP_SUB_SATURATING, "SUB_SATURATING"),
+    (OP_MUL_SATURATING, "MUL_SATURATING"),
+    (OP_STR_GET_SLICE, "STR_GET_SLICE"),
+    (OP_STR_LENGTH, "STR_LENGTH"),
+    (OP_STR_FIND, "STR_FIND"),
+    (OP_STR_CONTAINS, "STR_CONTAINS"),
+    (OP_STR_STARTS_WITH, "STR_STARTS_WITH"),
+    (OP_STR_ENDS_WITH, "STR_ENDS_WITH"),
+    (OP_STR_REPLACE, "STR_REPLACE"),
+    (OP_STR_SPLIT, "STR_SPLIT"),
+    (OP_STR_JOIN, "STR_JOIN"),
+    (OP_STR_UPPER, "STR_UPPER"),
+    (OP_STR_LOWER, "STR_LOWER"),
+    (OP_STR_TRIM, "STR_TRIM"),
+    (OP_STR_REPEAT, "STR_REPEAT"),
+    (OP_CAST, "CAST"),
+    (OP_LIST_NEW, "LIST_NEW"),
+    (OP_LIST_PUSH, "LIST_PUSH"),
+    (OP_LIST_POP, "LIST_POP"),
+    (OP_LIST_GET, "LIST_GET"),
+    (OP_LIST_SET, "LIST_SET"),
+    (OP_LIST_LENGTH, "LIST_LENGTH"),
+    (OP_LIST_SLICE, "LIST_SLICE"),
+    (OP_MAP_NEW, "MAP_NEW"),
+    (OP_MAP_GET, "MAP_GET"),
+    (OP_MAP_SET, "MAP_SET"),
+    (OP_MAP_DELETE, "MAP_DELETE"),
+    (OP_MAP_CONTAINS, "MAP_CONTAINS"),
+    (OP_MAP_KEYS, "MAP_KEYS"),
+    (OP_AND, "AND"),
+    (OP_OR, "OR"),
+    (OP_XOR, "XOR"),
+    (OP_NOT, "NOT"),
+    (OP_SHL, "SHL"),
+    (OP_SHR, "SHR"),
+    (OP_MATH, "MATH"),
+    (OP_CMP, "CMP"),
+    (OP_LABEL, "LABEL"),
+    (OP_JUMP, "JMP"),
+    (OP_JUMP_IF_TRUE, "JMP_IF_TRUE"),
+    (OP_JUMP_IF_FALSE, "JMP_IF_FALSE"),
+    (OP_CALL, "CALL"),
+    (OP_RETURN, "RET"),
+    (OP_FUNC, "FUNC"),
+    (OP_DISPLAY_STDOUT, "DISPLAY_STDOUT"),
+    (OP_DISPLAY_STDERR, "DISPLAY_STDERR"),
+    (OP_INPUT, "INPUT"),
+    (OP_EXIT, "EXIT"),
+];
+
+pub const PUSH_TYPES: &[(u8, &str)] = &[
+    (PUSH_TYPE_INTEGER, "INT"),
+    (PUSH_TYPE_FLOAT, "FLOAT"),
+    (PUSH_TYPE_STRING, "STRING"),
+    (PUSH_TYPE_BOOLEAN, "BOOL"),
+    (PUSH_TYPE_INTEGER_POWER, "INT_POW"),
+    (PUSH_TYPE_INTEGER_POWER_SUB, "INT_POW_SUB"),
+    (PUSH_TYPE_NULL, "NULL"),
+    (PUSH_TYPE_BIG_INTEGER, "BIGINT"),
+];
+
+pub const CAST_TYPES: &[(u8, &str)] = &[
+    (CAST_TYPE_ITOS, "ITOS"),
+    (CAST_TYPE_STOI, "STOI"),
+    (CAST_TYPE_ITOBIG, "ITOBIG"),
+    (CAST_TYPE_BIGTOI, "BIGTOI"),
+    (CAST_TYPE_ITOF, "ITOF"),
+    (CAST_TYPE_FTOI, "FTOI"),
+    (CAST_TYPE_FTOI_ROUND, "FTOI_ROUND"),
+    (CAST_TYPE_FTOI_FLOOR, "FTOI_FLOOR"),
+    (CAST_TYPE_FTOI_CEIL, "FTOI_CEIL"),
+    (CAST_TYPE_BOOLTOI, "BOOLTOI"),
+    (CAST_TYPE_ITOBOOL, "ITOBOOL"),
+    (CAST_TYPE_FORMAT, "FORMAT"),
+    (CAST_TYPE_PARSE_INT, "PARSE_INT"),
+    (CAST_TYPE_PARSE_FLOAT, "PARSE_FLOAT"),
+];
+
+pub const CMP_TYPES: &[(u8, &str)] = &[
+    (CMP_TYPE_EQUAL, "EQUAL"),
+    (CMP_TYPE_NOT_EQUAL, "NOT_EQUAL"),
+    (CMP_TYPE_LESS_THAN, "LESS_THAN"),
+    (CMP_TYPE_GREATER_THAN, "GREATER_THAN"),
+    (CMP_TYPE_LESS_EQUAL, "LESS_EQUAL"),
+    (CMP_TYPE_GREATER_EQUAL, "GREATER_EQUAL"),
+];
+
+pub const MATH_TYPES: &[(u8, &str)] = &[
+    (MATH_TYPE_SQRT, "SQRT"),
+    (MATH_TYPE_POW, "POW"),
+    (MATH_TYPE_ABS, "ABS"),
+    (MATH_TYPE_FLOOR, "FLOOR"),
+    (MATH_TYPE_CEIL, "CEIL"),
+    (MATH_TYPE_ROUND, "ROUND"),
+    (MATH_TYPE_SIN, "SIN"),
+    (MATH_TYPE_COS, "COS"),
+    (MATH_TYPE_TAN, "TAN"),
+    (MATH_TYPE_ATAN2, "ATAN2"),
+    (MATH_TYPE_EXP, "EXP"),
+    (MATH_TYPE_LN, "LN"),
+    (MATH_TYPE_MIN, "MIN"),
+    (MATH_TYPE_MAX, "MAX"),
+];
+
+pub fn name_of(table: &[(u8, &'static str)], code: u8) -> Option<&'static str> {
+    table
+        .iter()
+        .find(|(entry, _)| *entry == code)
+        .map(|(_, name)| *name)
+}
+
+pub fn code_of(table: &[(u8, &str)], name: &str) -> Option<u8> {
+    table
+        .iter()
+        .find(|(_, entry)| entry.eq_ignore_ascii_case(name))
+        .map(|(code, _)| *code)
+}
+
+pub fn opcode_name(opcode: u8) -> &'static str {
+    name_of(OPCODES, opcode).unwrap_or("UNKNOWN")
+}
@@ -0,0 +1,52 @@
+/// Parses a decimal or `0x`-prefixed hexadecimal integer with an optional
+/// sign. Used for integer operands in assembly and by `CAST PARSE_INT`.
+pub(crate) fn parse_integer(word: &str) -> Option<i64> {
+    let (negative, digits) = match word.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, word.strip_prefix('+').unwrap_or(word)),
+    };
+
+    let (digits, radix) = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => (hex, 16),
+        None => (digits, 10),
+    };
+
+    // `from_str_radix` takes a sign of its own, which would let "--5" through.
+    if digits.starts_with(['+', '-']) {
+        return None;
+    }
+
+    let magnitude = i128::from_str_radix(digits, radix).ok()?;
+    let value = if negative {
+        magnitude.checked_neg()?
+    } else {
+        magnitude
+    };
+
+    i64::try_from(value).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_integer_accepts_one_sign() {
+        assert_eq!(parse_integer("42"), Some(42));
+        assert_eq!(parse_integer("+42"), Some(42));
+        assert_eq!(parse_integer("-0x10"), Some(-16));
+        assert_eq!(parse_integer("-9223372036854775808"), Some(i64::MIN));
+        assert_eq!(parse_integer("9223372036854775808"), None);
+
+        for text in ["--5", "+-5", "-+5", "0x-5", "0x+5", "-", "0x", ""] {
+            assert_eq!(parse_integer(text), None, "{:?}", text);
+        }
+        assert_eq!(
+            parse_integer("--170141183460469231731687303715884105728"),
+            None
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::LoadError,
+    instruction::{Instruction, decode},
+};
+
+/// A decoded and validated program, ready to be run by a `VM`. Every jump and
+/// call in a `Program` refers to a label that exists exactly once.
+#[derive(Debug, Clone)]
+pub struct Program {
+    instructions: Vec<(usize, Instruction)>,
+    labels: HashMap<String, usize>,
+}
+
+impl Program {
+    pub fn load(bytecode: &[u8]) -> Result<Program, LoadError> {
+        let instructions = decode(bytecode)?;
+
+        let mut labels = HashMap::new();
+        for (index, (offset, instruction)) in instructions.iter().enumerate() {
+            if let Some(name) = instruction.label_name()
+                && let Some(previous) = labels.insert(name.to_string(), index)
+            {
+                return Err(LoadError::DuplicateLabel {
+                    name: name.to_string(),
+                    offset: *offset,
+                    previous: instructions[previous].0,
+                });
+            }
+        }
+
+        for (offset, instruction) in &instructions {
+            if let Some(target) = instruction.target()
+                && target.index.is_none()
+            {
+                return Err(LoadError::UndefinedLabel {
+                    name: target.label.clone(),
+                    offset: *offset,
+                });
+            }
+        }
+
+        Ok(Program {
+            instructions,
+            labels,
+        })
+    }
+
+    pub fn instructions(&self) -> &[(usize, Instruction)] {
+        &self.instructions
+    }
+
+    pub fn labels(&self) -> &HashMap<String, usize> {
+        &self.labels
+    }
+}
@@ -1,79 +1,96 @@
-use std::{collections::HashMap, io::Write};
-
-const OP_DEBUG: u8 = 0x00;
-const OP_PUSH: u8 = 0x01;
-const OP_LOAD: u8 = 0x02;
-const OP_STORE: u8 = 0x03;
-const OP_DUP: u8 = 0x04;
-const OP_SWAP: u8 = 0x05;
-const OP_POP: u8 = 0x06;
-const OP_FREE: u8 = 0x07;
-const OP_LOAD_REF: u8 = 0x08;
-const OP_STORE_REF: u8 = 0x09;
-
-const OP_ADD: u8 = 0x10;
-const OP_SUB: u8 = 0x11;
-const OP_MUL: u8 = 0x12;
-const OP_DIV: u8 = 0x13;
-const OP_MOD: u8 = 0x14;
-
-const OP_STR_GET_SLICE: u8 = 0x20;
-const OP_STR_LENGTH: u8 = 0x21;
-
-const OP_CAST: u8 = 0x30;
-
-const OP_CMP: u8 = 0xD0;
-
-const OP_LABEL: u8 = 0xE0;
-const OP_JUMP: u8 = 0xE1;
-const OP_JUMP_IF_TRUE: u8 = 0xE2;
-const OP_JUMP_IF_FALSE: u8 = 0xE3;
-const OP_CALL: u8 = 0xE4;
-const OP_RETURN: u8 = 0xE5;
-
-const OP_DISPLAY_STDOUT: u8 = 0xF0;
-const OP_DISPLAY_STDERR: u8 = 0xF1;
-const OP_INPUT: u8 = 0xF2;
-const OP_EXIT: u8 = 0xFF;
-
-const PUSH_TYPE_INTEGER: u8 = 0x01;
-const PUSH_TYPE_FLOAT: u8 = 0x02;
-const PUSH_TYPE_STRING: u8 = 0x03;
-const PUSH_TYPE_BOOLEAN: u8 = 0x04;
-const PUSH_TYPE_INTEGER_POWER: u8 = 0x05;
-const PUSH_TYPE_INTEGER_POWER_SUB: u8 = 0x06;
-
-const CAST_TYPE_ITOS: u8 = 0x01;
-const CAST_TYPE_STOI: u8 = 0x02;
-
-const CMP_TYPE_EQUAL: u8 = 0x01;
-const CMP_TYPE_NOT_EQUAL: u8 = 0x02;
-const CMP_TYPE_LESS_THAN: u8 = 0x03;
-const CMP_TYPE_GREATER_THAN: u8 = 0x04;
-const CMP_TYPE_LESS_EQUAL: u8 = 0x05;
-const CMP_TYPE_GREATER_EQUAL: u8 = 0x06;
-
-#[derive(Debug, Clone)]
-enum IVMType {
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    io::{BufRead, Write},
+    ops::{Add, Sub},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    bigint::{BigInt, MAX_BITS},
+    error::{ErrorKind, LoadError, VmError},
+    host::{IoHost, ProcessIo},
+    instruction::{Instruction, Target},
+    opcode::*,
+    parse::parse_integer,
+    program::Program,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IVMType {
     Integer { value: i64 },
     Float { value: f64 },
     String { value: String },
     Boolean { value: bool },
+    List { value: Vec<IVMType> },
+    Map { value: BTreeMap<String, IVMType> },
+    Null,
+    BigInteger { value: BigInt },
 }
 
 impl IVMType {
-    fn compare(&self, other: &IVMType, cmp_type: u8) -> Option<bool> {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            IVMType::Integer { .. } => "Integer",
+            IVMType::Float { .. } => "Float",
+            IVMType::String { .. } => "String",
+            IVMType::Boolean { .. } => "Boolean",
+            IVMType::List { .. } => "List",
+            IVMType::Map { .. } => "Map",
+            IVMType::Null => "Null",
+            IVMType::BigInteger { .. } => "BigInteger",
+        }
+    }
+
+    fn incompatible(&self, other: &IVMType) -> ErrorKind {
+        ErrorKind::IncompatibleTypes {
+            lhs: self.type_name(),
+            rhs: other.type_name(),
+        }
+    }
+
+    /// Returns both operands as floats when one is a float and the other is a
+    /// float or an integer, which is how mixed arithmetic is promoted.
+    fn float_operands(&self, other: &IVMType) -> Option<(f64, f64)> {
         match (self, other) {
-            (IVMType::Integer { value: lhs }, IVMType::Integer { value: rhs }) => match cmp_type {
+            (IVMType::Float { value: lhs }, IVMType::Float { value: rhs }) => Some((*lhs, *rhs)),
+            (IVMType::Float { value: lhs }, IVMType::Integer { value: rhs }) => {
+                Some((*lhs, *rhs as f64))
+            }
+            (IVMType::Integer { value: lhs }, IVMType::Float { value: rhs }) => {
+                Some((*lhs as f64, *rhs))
+            }
+            _ => None,
+        }
+    }
+
+    fn compare(&self, other: &IVMType, cmp_type: u8) -> Result<bool, ErrorKind> {
+        if let Some((lhs, rhs)) = self.float_operands(other) {
+            // NaN is unequal to everything, itself included, and has no order.
+            // Infinities order below and above every finite value.
+            let result = match cmp_type {
                 CMP_TYPE_EQUAL => Some(lhs == rhs),
                 CMP_TYPE_NOT_EQUAL => Some(lhs != rhs),
+                CMP_TYPE_LESS_THAN
+                | CMP_TYPE_GREATER_THAN
+                | CMP_TYPE_LESS_EQUAL
+                | CMP_TYPE_GREATER_EQUAL
+                    if lhs.is_nan() || rhs.is_nan() =>
+                {
+                    return Err(ErrorKind::UnorderedComparison);
+                }
                 CMP_TYPE_LESS_THAN => Some(lhs < rhs),
                 CMP_TYPE_GREATER_THAN => Some(lhs > rhs),
                 CMP_TYPE_LESS_EQUAL => Some(lhs <= rhs),
                 CMP_TYPE_GREATER_EQUAL => Some(lhs >= rhs),
                 _ => None,
-            },
-            (IVMType::Float { value: lhs }, IVMType::Float { value: rhs }) => match cmp_type {
+            };
+            return result.ok_or(ErrorKind::InvalidComparison(cmp_type));
+        }
+
+        let result = match (self, other) {
+            (IVMType::Integer { value: lhs }, IVMType::Integer { value: rhs }) => match cmp_type {
                 CMP_TYPE_EQUAL => Some(lhs == rhs),
                 CMP_TYPE_NOT_EQUAL => Some(lhs != rhs),
                 CMP_TYPE_LESS_THAN => Some(lhs < rhs),
@@ -96,980 +113,2001 @@ impl IVMType {
                 CMP_TYPE_NOT_EQUAL => Some(lhs != rhs),
                 _ => None,
             },
-            _ => None,
-        }
+            (IVMType::List { value: lhs }, IVMType::List { value: rhs }) => match cmp_type {
+                CMP_TYPE_EQUAL => Some(lhs == rhs),
+                CMP_TYPE_NOT_EQUAL => Some(lhs != rhs),
+                _ => None,
+            },
+            (IVMType::Map { value: lhs }, IVMType::Map { value: rhs }) => match cmp_type {
+                CMP_TYPE_EQUAL => Some(lhs == rhs),
+                CMP_TYPE_NOT_EQUAL => Some(lhs != rhs),
+                _ => None,
+            },
+            (IVMType::Null, IVMType::Null) => match cmp_type {
+                CMP_TYPE_EQUAL => Some(true),
+                CMP_TYPE_NOT_EQUAL => Some(false),
+                _ => None,
+            },
+            _ => match (self.to_big_integer(), other.to_big_integer()) {
+                (Some(lhs), Some(rhs)) => match cmp_type {
+                    CMP_TYPE_EQUAL => Some(lhs == rhs),
+                    CMP_TYPE_NOT_EQUAL => Some(lhs != rhs),
+                    CMP_TYPE_LESS_THAN => Some(lhs < rhs),
+                    CMP_TYPE_GREATER_THAN => Some(lhs > rhs),
+                    CMP_TYPE_LESS_EQUAL => Some(lhs <= rhs),
+                    CMP_TYPE_GREATER_EQUAL => Some(lhs >= rhs),
+                    _ => None,
+                },
+                _ => return Err(self.incompatible(other)),
+            },
+        };
+
+        result.ok_or(ErrorKind::InvalidComparison(cmp_type))
     }
 
-    fn add(&self, other: &IVMType) -> Option<IVMType> {
+    fn add(&self, other: &IVMType) -> Result<IVMType, ErrorKind> {
+        if let Some((lhs, rhs)) = self.float_operands(other) {
+            return Ok(IVMType::Float { value: lhs + rhs });
+        }
+
         match (self, other) {
-            (IVMType::Integer { value: lhs }, IVMType::Integer { value: rhs }) => {
-                Some(IVMType::Integer { value: lhs + rhs })
-            }
-            (IVMType::Float { value: lhs }, IVMType::Float { value: rhs }) => {
-                Some(IVMType::Float { value: lhs + rhs })
-            }
+            (IVMType::Integer { value: lhs }, IVMType::Integer { value: rhs }) => lhs
+                .checked_add(*rhs)
+                .map(|value| IVMType::Integer { value })
+                .ok_or(ErrorKind::IntegerOverflow),
             (IVMType::String { value: lhs }, IVMType::String { value: rhs }) => {
-                Some(IVMType::String {
+                Ok(IVMType::String {
                     value: format!("{}{}", lhs, rhs),
                 })
             }
-            _ => None,
+            _ => self.big_integer_op(other, |lhs, rhs| Ok(lhs + rhs)),
         }
     }
 
-    fn sub(&self, other: &IVMType) -> Option<IVMType> {
+    fn sub(&self, other: &IVMType) -> Result<IVMType, ErrorKind> {
+        if let Some((lhs, rhs)) = self.float_operands(other) {
+            return Ok(IVMType::Float { value: lhs - rhs });
+        }
+
         match (self, other) {
-            (IVMType::Integer { value: lhs }, IVMType::Integer { value: rhs }) => {
-                Some(IVMType::Integer { value: lhs - rhs })
-            }
-            (IVMType::Float { value: lhs }, IVMType::Float { value: rhs }) => {
-                Some(IVMType::Float { value: lhs - rhs })
-            }
-            _ => None,
+            (IVMType::Integer { value: lhs }, IVMType::Integer { value: rhs }) => lhs
+                .checked_sub(*rhs)
+                .map(|value| IVMType::Integer { value })
+                .ok_or(ErrorKind::IntegerOverflow),
+            _ => self.big_integer_op(other, |lhs, rhs| Ok(lhs - rhs)),
         }
     }
 
-    fn mul(&self, other: &IVMType) -> Option<IVMType> {
+    fn mul(&self, other: &IVMType) -> Result<IVMType, ErrorKind> {
+        if let Some((lhs, rhs)) = self.float_operands(other) {
+            return Ok(IVMType::Float { value: lhs * rhs });
+        }
+
         match (self, other) {
-            (IVMType::Integer { value: lhs }, IVMType::Integer { value: rhs }) => {
-                Some(IVMType::Integer { value: lhs * rhs })
-            }
-            (IVMType::Float { value: lhs }, IVMType::Float { value: rhs }) => {
-                Some(IVMType::Float { value: lhs * rhs })
-            }
-            (IVMType::String { value: lhs }, IVMType::Integer { value: rhs }) => {
-                if *rhs < 0 {
-                    return None;
-                }
-                Some(IVMType::String {
+            (IVMType::Integer { value: lhs }, IVMType::Integer { value: rhs }) => lhs
+                .checked_mul(*rhs)
+                .map(|value| IVMType::Integer { value })
+                .ok_or(ErrorKind::IntegerOverflow),
+            (IVMType::String { value: lhs }, IVMType::Integer { value: rhs }) if *rhs >= 0 => {
+                Ok(IVMType::String {
                     value: lhs.repeat(*rhs as usize),
                 })
             }
-            _ => None,
+            _ => self.big_integer_op(other, |lhs, rhs| {
+                // The product has at least this many bits, so there is no
+                // need to compute it.
+                if lhs.bits() + rhs.bits() > MAX_BITS + 1 {
+                    return Err(ErrorKind::BigIntegerTooLarge);
+                }
+                Ok(lhs * rhs)
+            }),
         }
     }
 
-    fn div(&self, other: &IVMType) -> Option<IVMType> {
+    fn div(&self, other: &IVMType) -> Result<IVMType, ErrorKind> {
+        if let Some((lhs, rhs)) = self.float_operands(other) {
+            if rhs == 0.0 {
+                return Err(ErrorKind::DivisionByZero);
+            }
+            return Ok(IVMType::Float { value: lhs / rhs });
+        }
+
         match (self, other) {
             (IVMType::Integer { value: lhs }, IVMType::Integer { value: rhs }) => {
                 if *rhs == 0 {
-                    return None;
+                    return Err(ErrorKind::DivisionByZero);
                 }
-                Some(IVMType::Integer { value: lhs / rhs })
+                lhs.checked_div(*rhs)
+                    .map(|value| IVMType::Integer { value })
+                    .ok_or(ErrorKind::IntegerOverflow)
             }
-            (IVMType::Float { value: lhs }, IVMType::Float { value: rhs }) => {
-                if *rhs == 0.0 {
-                    return None;
+            _ => self.big_integer_op(other, |lhs, rhs| {
+                lhs.divrem(rhs)
+                    .map(|(quotient, _)| quotient)
+                    .ok_or(ErrorKind::DivisionByZero)
+            }),
+        }
+    }
+
+    fn modulo(&self, other: &IVMType) -> Result<IVMType, ErrorKind> {
+        match (self, other) {
+            (IVMType::Integer { value: lhs }, IVMType::Integer { value: rhs }) => {
+                if *rhs == 0 {
+                    return Err(ErrorKind::DivisionByZero);
                 }
-                Some(IVMType::Float { value: lhs / rhs })
+                lhs.checked_rem(*rhs)
+                    .map(|value| IVMType::Integer { value })
+                    .ok_or(ErrorKind::IntegerOverflow)
             }
+            _ => self.big_integer_op(other, |lhs, rhs| {
+                lhs.divrem(rhs)
+                    .map(|(_, remainder)| remainder)
+                    .ok_or(ErrorKind::DivisionByZero)
+            }),
+        }
+    }
+
+    fn to_big_integer(&self) -> Option<BigInt> {
+        match self {
+            IVMType::Integer { value } => Some(BigInt::from(*value)),
+            IVMType::BigInteger { value } => Some(value.clone()),
             _ => None,
         }
     }
 
-    fn modulo(&self, other: &IVMType) -> Option<IVMType> {
+    /// Applies `op` to two integers of which at least one is a `BigInteger`,
+    /// promoting an `Integer` operand first. Fails if the result is larger
+    /// than `MAX_BITS`.
+    fn big_integer_op(
+        &self,
+        other: &IVMType,
+        op: impl FnOnce(&BigInt, &BigInt) -> Result<BigInt, ErrorKind>,
+    ) -> Result<IVMType, ErrorKind> {
+        match (self.to_big_integer(), other.to_big_integer()) {
+            (Some(lhs), Some(rhs)) => {
+                let value = op(&lhs, &rhs)?;
+                if value.bits() > MAX_BITS {
+                    return Err(ErrorKind::BigIntegerTooLarge);
+                }
+                Ok(IVMType::BigInteger { value })
+            }
+            _ => Err(self.incompatible(other)),
+        }
+    }
+
+    /// Applies a bitwise operation to two integers, or the matching logical
+    /// operation to two booleans.
+    fn bitwise(
+        &self,
+        other: &IVMType,
+        integer_op: fn(i64, i64) -> i64,
+        boolean_op: fn(bool, bool) -> bool,
+    ) -> Result<IVMType, ErrorKind> {
         match (self, other) {
             (IVMType::Integer { value: lhs }, IVMType::Integer { value: rhs }) => {
-                if *rhs == 0 {
-                    return None;
+                Ok(IVMType::Integer {
+                    value: integer_op(*lhs, *rhs),
+                })
+            }
+            (IVMType::Boolean { value: lhs }, IVMType::Boolean { value: rhs }) => {
+                Ok(IVMType::Boolean {
+                    value: boolean_op(*lhs, *rhs),
+                })
+            }
+            _ => Err(self.incompatible(other)),
+        }
+    }
+
+    /// Applies an integer-only operation whose overflow behaviour is part of
+    /// the operation itself, such as `i64::wrapping_add`.
+    fn integer_op(&self, other: &IVMType, op: fn(i64, i64) -> i64) -> Result<IVMType, ErrorKind> {
+        match (self, other) {
+            (IVMType::Integer { value: lhs }, IVMType::Integer { value: rhs }) => {
+                Ok(IVMType::Integer {
+                    value: op(*lhs, *rhs),
+                })
+            }
+            _ => Err(self.incompatible(other)),
+        }
+    }
+}
+
+impl fmt::Display for IVMType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IVMType::Integer { value } => write!(f, "{}", value),
+            IVMType::Float { value } => write!(f, "{}", value),
+            IVMType::String { value } => write!(f, "{}", value),
+            IVMType::Boolean { value } => write!(f, "{}", value),
+            IVMType::Null => write!(f, "null"),
+            IVMType::BigInteger { value } => write!(f, "{}", value),
+            IVMType::List { value } => {
+                write!(f, "[")?;
+                for (index, item) in value.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_item(f, item)?;
                 }
-                Some(IVMType::Integer { value: lhs % rhs })
+                write!(f, "]")
+            }
+            IVMType::Map { value } => {
+                write!(f, "{{")?;
+                for (index, (key, item)) in value.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}: ", key)?;
+                    write_item(f, item)?;
+                }
+                write!(f, "}}")
             }
-            _ => None,
         }
     }
 }
 
-pub struct VM {
-    bytecode: Vec<u8>,
-    index: usize,
-    memory: HashMap<String, IVMType>,
-    stack: Vec<IVMType>,
-    labels: HashMap<String, usize>,
-    calls: Vec<usize>,
+fn write_item(f: &mut fmt::Formatter<'_>, item: &IVMType) -> fmt::Result {
+    match item {
+        IVMType::String { value } => write!(f, "{:?}", value),
+        item => write!(f, "{}", item),
+    }
 }
 
-impl VM {
-    pub fn new(bytecode: Vec<u8>) -> Self {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// The program ran past its last instruction.
+    Halted,
+    /// The step limit or timeout was reached. Calling `run` again resumes the
+    /// program from the next instruction.
+    BudgetExhausted,
+    /// The program ran `EXIT` with this code. The program is finished and
+    /// calling `run` again does nothing.
+    Exited(i32),
+}
+
+/// The I/O a program may perform. Instructions that need a capability the VM
+/// was not given fail with a permission error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Permissions {
+    /// `INPUT`
+    pub stdin: bool,
+    /// `DISPLAY_STDOUT` and `DEBUG`
+    pub stdout: bool,
+    /// `DISPLAY_STDERR`
+    pub stderr: bool,
+}
+
+impl Permissions {
+    pub const ALL: Permissions = Permissions {
+        stdin: true,
+        stdout: true,
+        stderr: true,
+    };
+
+    pub const NONE: Permissions = Permissions {
+        stdin: false,
+        stdout: false,
+        stderr: false,
+    };
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Permissions::ALL
+    }
+}
+
+/// Caps on the memory a program can use. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default)]
+struct Limits {
+    stack_depth: Option<usize>,
+    call_depth: Option<usize>,
+    variables: Option<usize>,
+    string_bytes: Option<usize>,
+    items: Option<usize>,
+}
+
+impl Limits {
+    /// Whether the VM has to keep count of the `Usage` the program holds.
+    fn counts_usage(&self) -> bool {
+        self.string_bytes.is_some() || self.items.is_some()
+    }
+}
+
+/// The memory held by values, as counted against `Limits`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Usage {
+    /// Bytes in strings, including map keys.
+    string_bytes: usize,
+    /// Items in lists and maps, including those of nested lists and maps.
+    items: usize,
+}
+
+impl Usage {
+    fn string(bytes: usize) -> Self {
+        Usage {
+            string_bytes: bytes,
+            items: 0,
+        }
+    }
+
+    fn items(count: usize) -> Self {
+        Usage {
+            string_bytes: 0,
+            items: count,
+        }
+    }
+
+    fn of(value: &IVMType) -> Self {
+        match value {
+            IVMType::String { value } => Usage::string(value.len()),
+            IVMType::List { value } => {
+                value.iter().fold(Usage::items(value.len()), |usage, item| {
+                    usage + Usage::of(item)
+                })
+            }
+            IVMType::Map { value } => value
+                .iter()
+                .fold(Usage::items(value.len()), |usage, (key, item)| {
+                    usage + Usage::string(key.len()) + Usage::of(item)
+                }),
+            _ => Usage::default(),
+        }
+    }
+}
+
+impl Add for Usage {
+    type Output = Usage;
+
+    fn add(self, other: Usage) -> Usage {
+        Usage {
+            string_bytes: self.string_bytes.saturating_add(other.string_bytes),
+            items: self.items.saturating_add(other.items),
+        }
+    }
+}
+
+impl Sub for Usage {
+    type Output = Usage;
+
+    fn sub(self, other: Usage) -> Usage {
+        Usage {
+            string_bytes: self.string_bytes.saturating_sub(other.string_bytes),
+            items: self.items.saturating_sub(other.items),
+        }
+    }
+}
+
+/// An active `CALL`.
+#[derive(Debug)]
+pub struct Frame {
+    return_address: usize,
+    locals: HashMap<String, IVMType>,
+    stack_base: usize,
+    returns: Option<u8>,
+}
+
+impl Frame {
+    /// The index of the instruction after the `CALL`, where `RETURN` resumes.
+    pub fn return_address(&self) -> usize {
+        self.return_address
+    }
+
+    pub fn locals(&self) -> &HashMap<String, IVMType> {
+        &self.locals
+    }
+}
+
+pub struct VMBuilder {
+    program: Program,
+    streams: ProcessIo,
+    io: Option<Box<dyn IoHost>>,
+    max_steps: Option<u64>,
+    timeout: Option<Duration>,
+    limits: Limits,
+    permissions: Permissions,
+}
+
+impl VMBuilder {
+    pub fn new(program: Program) -> Self {
         Self {
-            bytecode,
-            index: 0,
-            memory: HashMap::new(),
-            stack: Vec::new(),
-            labels: HashMap::new(),
-            calls: Vec::new(),
+            program,
+            streams: ProcessIo::default(),
+            io: None,
+            max_steps: None,
+            timeout: None,
+            limits: Limits::default(),
+            permissions: Permissions::default(),
         }
     }
 
-    fn can_advance(&self, steps: usize) -> bool {
-        self.index + steps <= self.bytecode.len()
+    /// Replaces the process stdin. Discards any host set with `io`, keeping
+    /// the other process streams.
+    pub fn stdin(mut self, stdin: impl BufRead + 'static) -> Self {
+        self.streams.stdin = Some(Box::new(stdin));
+        self.io = None;
+        self
     }
 
-    pub fn run(&mut self, resolve: bool) {
-        let stdin = std::io::stdin();
-        let stdout = std::io::stdout();
-        let stderr = std::io::stderr();
+    /// Replaces the process stdout. Discards any host set with `io`, keeping
+    /// the other process streams.
+    pub fn stdout(mut self, stdout: impl Write + 'static) -> Self {
+        self.streams.stdout = Some(Box::new(stdout));
+        self.io = None;
+        self
+    }
 
-        self.index = 0;
+    /// Replaces the process stderr. Discards any host set with `io`, keeping
+    /// the other process streams.
+    pub fn stderr(mut self, stderr: impl Write + 'static) -> Self {
+        self.streams.stderr = Some(Box::new(stderr));
+        self.io = None;
+        self
+    }
 
-        while self.index < self.bytecode.len() {
-            let opcode = self.bytecode[self.index];
-            self.index += 1;
+    /// Sends all of the program's I/O to `io`, discarding any streams set
+    /// with `stdin`, `stdout` or `stderr`.
+    pub fn io(mut self, io: impl IoHost + 'static) -> Self {
+        self.streams = ProcessIo::default();
+        self.io = Some(Box::new(io));
+        self
+    }
 
-            match opcode {
-                OP_DEBUG => {
-                    if resolve {
-                        continue;
-                    }
+    /// Limits each call to `run` to executing `steps` instructions.
+    pub fn max_steps(mut self, steps: u64) -> Self {
+        self.max_steps = Some(steps);
+        self
+    }
 
-                    println!("Stack: {:?}", self.stack);
-                    println!("Memory: {:?}", self.memory);
-                    println!("Labels: {:?}", self.labels);
-                    println!("Calls: {:?}", self.calls);
-                }
-                OP_PUSH => {
-                    if !self.can_advance(1) {
-                        println!("Error: Incomplete PUSH instruction");
-                        break;
-                    }
+    /// Limits each call to `run` to `timeout` of wall-clock time. The clock is
+    /// read before each instruction, so one that blocks, such as `INPUT`
+    /// waiting for a line, can overrun it.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
 
-                    let datatype = self.bytecode[self.index];
-                    self.index += 1;
+    /// Limits the number of values on the stack, across all call frames.
+    pub fn max_stack_depth(mut self, depth: usize) -> Self {
+        self.limits.stack_depth = Some(depth);
+        self
+    }
 
-                    match datatype {
-                        PUSH_TYPE_INTEGER => {
-                            if !self.can_advance(8) {
-                                println!("Error: Incomplete Integer data");
-                                break;
-                            }
+    /// Limits how many calls can be active at once.
+    pub fn max_call_depth(mut self, depth: usize) -> Self {
+        self.limits.call_depth = Some(depth);
+        self
+    }
 
-                            let int_bytes = &self.bytecode[self.index..self.index + 8];
-                            let int_value = i64::from_le_bytes(int_bytes.try_into().unwrap());
-                            self.index += 8;
+    /// Limits the number of variables, counting globals and the locals of
+    /// every active call.
+    pub fn max_variables(mut self, count: usize) -> Self {
+        self.limits.variables = Some(count);
+        self
+    }
 
-                            if resolve {
-                                continue;
-                            }
+    /// Limits the total length in bytes of the strings the program holds, on
+    /// the stack, in variables and inside lists and maps.
+    pub fn max_string_bytes(mut self, bytes: usize) -> Self {
+        self.limits.string_bytes = Some(bytes);
+        self
+    }
 
-                            self.stack.push(IVMType::Integer { value: int_value });
-                        }
-                        PUSH_TYPE_FLOAT => {
-                            if !self.can_advance(8) {
-                                println!("Error: Incomplete Float data");
-                                break;
-                            }
+    /// Limits the total number of items in the lists and maps the program
+    /// holds, counting the items of nested lists and maps.
+    pub fn max_collection_items(mut self, count: usize) -> Self {
+        self.limits.items = Some(count);
+        self
+    }
 
-                            let float_bytes = &self.bytecode[self.index..self.index + 8];
-                            let float_value = f64::from_le_bytes(float_bytes.try_into().unwrap());
-                            self.index += 8;
+    /// Sets the I/O the program may perform. Everything is allowed by default.
+    pub fn permissions(mut self, permissions: Permissions) -> Self {
+        self.permissions = permissions;
+        self
+    }
 
-                            if resolve {
-                                continue;
-                            }
+    pub fn build(self) -> VM {
+        let io = self.io.unwrap_or_else(|| Box::new(self.streams));
 
-                            self.stack.push(IVMType::Float { value: float_value });
-                        }
-                        PUSH_TYPE_STRING => {
-                            if !self.can_advance(4) {
-                                println!("Error: Incomplete String length data");
-                                break;
-                            }
+        VM {
+            program: Rc::new(self.program),
+            pc: 0,
+            memory: HashMap::new(),
+            stack: Vec::new(),
+            frames: Vec::new(),
+            io,
+            max_steps: self.max_steps,
+            timeout: self.timeout,
+            limits: self.limits,
+            variable_count: 0,
+            usage: Usage::default(),
+            permissions: self.permissions,
+        }
+    }
+}
 
-                            let str_len_bytes = &self.bytecode[self.index..self.index + 4];
-                            let str_len =
-                                u32::from_le_bytes(str_len_bytes.try_into().unwrap()) as usize;
-                            self.index += 4;
+pub struct VM {
+    program: Rc<Program>,
+    pc: usize,
+    memory: HashMap<String, IVMType>,
+    stack: Vec<IVMType>,
+    frames: Vec<Frame>,
+    io: Box<dyn IoHost>,
+    max_steps: Option<u64>,
+    timeout: Option<Duration>,
+    limits: Limits,
+    /// Globals plus the locals of every active call.
+    variable_count: usize,
+    /// Everything the program holds. Only kept up to date when a limit needs
+    /// it.
+    usage: Usage,
+    permissions: Permissions,
+}
 
-                            if !self.can_advance(str_len) {
-                                println!("Error: Incomplete String data");
-                                break;
-                            }
+impl VM {
+    pub fn load(bytecode: &[u8]) -> Result<Program, LoadError> {
+        Program::load(bytecode)
+    }
 
-                            let str_bytes = &self.bytecode[self.index..self.index + str_len];
-                            let str_value = String::from_utf8_lossy(str_bytes).to_string();
-                            self.index += str_len;
+    pub fn new(program: Program) -> Self {
+        VMBuilder::new(program).build()
+    }
 
-                            if resolve {
-                                continue;
-                            }
+    pub fn builder(program: Program) -> VMBuilder {
+        VMBuilder::new(program)
+    }
 
-                            self.stack.push(IVMType::String { value: str_value });
-                        }
-                        PUSH_TYPE_BOOLEAN => {
-                            if !self.can_advance(1) {
-                                println!("Error: Incomplete Boolean data");
-                                break;
-                            }
+    pub fn stack(&self) -> &[IVMType] {
+        &self.stack
+    }
 
-                            let bool_byte = self.bytecode[self.index];
-                            self.index += 1;
+    pub fn variable(&self, name: &str) -> Option<&IVMType> {
+        self.memory.get(name)
+    }
 
-                            if resolve {
-                                continue;
-                            }
+    pub fn variables(&self) -> &HashMap<String, IVMType> {
+        &self.memory
+    }
 
-                            let bool_value = match bool_byte {
-                                0x00 => false,
-                                0x01 => true,
-                                _ => {
-                                    println!("Error: Invalid Boolean value");
-                                    break;
-                                }
-                            };
+    /// The active calls, innermost last.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
 
-                            self.stack.push(IVMType::Boolean { value: bool_value });
-                        }
-                        PUSH_TYPE_INTEGER_POWER => {
-                            if !self.can_advance(1) {
-                                println!("Error: Incomplete Integer Power data");
-                                break;
-                            }
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
 
-                            let power_byte = self.bytecode[self.index];
-                            self.index += 1;
+    /// The index into `Program::instructions` of the next instruction to run.
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
 
-                            if resolve {
-                                continue;
-                            }
+    fn stack_base(&self) -> usize {
+        self.frames.last().map_or(0, |frame| frame.stack_base)
+    }
 
-                            let int_value = 2i64.pow(power_byte as u32);
+    /// Pops a value the instruction uses up, so it no longer counts against
+    /// the limits.
+    fn pop(&mut self) -> Result<IVMType, ErrorKind> {
+        let value = self.pop_kept()?;
+        self.release(self.usage_of(&value));
+        Ok(value)
+    }
 
-                            self.stack.push(IVMType::Integer { value: int_value });
-                        }
-                        PUSH_TYPE_INTEGER_POWER_SUB => {
-                            if !self.can_advance(1) {
-                                println!("Error: Incomplete Integer Power Subtraction data");
-                                break;
-                            }
+    /// Pops a value the instruction goes on to store or put in a list or map,
+    /// so it still counts against the limits.
+    fn pop_kept(&mut self) -> Result<IVMType, ErrorKind> {
+        if self.stack.len() <= self.stack_base() {
+            return Err(ErrorKind::StackUnderflow);
+        }
 
-                            let power_byte = self.bytecode[self.index];
-                            self.index += 1;
+        self.stack.pop().ok_or(ErrorKind::StackUnderflow)
+    }
 
-                            if resolve {
-                                continue;
-                            }
+    fn peek(&self) -> Result<&IVMType, ErrorKind> {
+        if self.stack.len() <= self.stack_base() {
+            return Err(ErrorKind::StackUnderflow);
+        }
 
-                            let int_value = 2i64.pow(power_byte as u32) - 1;
+        self.stack.last().ok_or(ErrorKind::StackUnderflow)
+    }
 
-                            self.stack.push(IVMType::Integer { value: int_value });
-                        }
-                        _ => println!("Unknown push data type: 0x{:02X}", datatype),
-                    }
-                }
-                OP_LOAD => {
-                    if !self.can_advance(1) {
-                        println!("Error: Incomplete LOAD instruction");
-                        break;
-                    }
+    fn peek_mut(&mut self) -> Result<&mut IVMType, ErrorKind> {
+        if self.stack.len() <= self.stack_base() {
+            return Err(ErrorKind::StackUnderflow);
+        }
 
-                    let str_len = self.bytecode[self.index] as usize;
-                    self.index += 1;
+        self.stack.last_mut().ok_or(ErrorKind::StackUnderflow)
+    }
 
-                    if !self.can_advance(str_len) {
-                        println!("Error: Incomplete LOAD key data");
-                        break;
-                    }
+    fn pop_integer(&mut self) -> Result<i64, ErrorKind> {
+        match self.pop()? {
+            IVMType::Integer { value } => Ok(value),
+            other => Err(ErrorKind::TypeMismatch {
+                expected: "Integer",
+                found: other.type_name(),
+            }),
+        }
+    }
 
-                    let key_bytes = &self.bytecode[self.index..self.index + str_len];
-                    let key = String::from_utf8_lossy(key_bytes).to_string();
-                    self.index += str_len;
+    /// Pops a float, or an integer converted to a float.
+    fn pop_float(&mut self) -> Result<f64, ErrorKind> {
+        match self.pop()? {
+            IVMType::Float { value } => Ok(value),
+            IVMType::Integer { value } => Ok(value as f64),
+            other => Err(ErrorKind::TypeMismatch {
+                expected: "Float",
+                found: other.type_name(),
+            }),
+        }
+    }
 
-                    if resolve {
-                        continue;
-                    }
+    fn pop_string(&mut self) -> Result<String, ErrorKind> {
+        match self.pop()? {
+            IVMType::String { value } => Ok(value),
+            other => Err(ErrorKind::TypeMismatch {
+                expected: "String",
+                found: other.type_name(),
+            }),
+        }
+    }
 
-                    let value = self.memory.get(&key);
-                    match value {
-                        Some(val) => self.stack.push(val.clone()),
-                        None => println!("Error: Key '{}' not found in memory", key),
-                    }
-                }
-                OP_LOAD_REF => {
-                    if resolve {
-                        continue;
-                    }
+    fn pop_list(&mut self) -> Result<Vec<IVMType>, ErrorKind> {
+        match self.pop()? {
+            IVMType::List { value } => Ok(value),
+            other => Err(ErrorKind::TypeMismatch {
+                expected: "List",
+                found: other.type_name(),
+            }),
+        }
+    }
 
-                    let loc = match self.stack.pop() {
-                        Some(IVMType::String { value }) => value,
-                        _ => {
-                            println!("Error: Expected String on stack for LOAD_REF");
-                            break;
-                        }
-                    };
+    /// Borrows the list at the top of the stack without popping it.
+    fn peek_list(&self) -> Result<&[IVMType], ErrorKind> {
+        match self.peek()? {
+            IVMType::List { value } => Ok(value),
+            other => Err(ErrorKind::TypeMismatch {
+                expected: "List",
+                found: other.type_name(),
+            }),
+        }
+    }
 
-                    let value = self.memory.get(&loc);
-                    match value {
-                        Some(val) => self.stack.push(val.clone()),
-                        None => println!("Error: Key '{}' not found in memory", loc),
-                    }
+    /// Borrows the list at the top of the stack to modify it in place.
+    fn peek_list_mut(&mut self) -> Result<&mut Vec<IVMType>, ErrorKind> {
+        match self.peek_mut()? {
+            IVMType::List { value } => Ok(value),
+            other => Err(ErrorKind::TypeMismatch {
+                expected: "List",
+                found: other.type_name(),
+            }),
+        }
+    }
+
+    /// Borrows the map at the top of the stack without popping it.
+    fn peek_map(&self) -> Result<&BTreeMap<String, IVMType>, ErrorKind> {
+        match self.peek()? {
+            IVMType::Map { value } => Ok(value),
+            other => Err(ErrorKind::TypeMismatch {
+                expected: "Map",
+                found: other.type_name(),
+            }),
+        }
+    }
+
+    /// Borrows the map at the top of the stack to modify it in place.
+    fn peek_map_mut(&mut self) -> Result<&mut BTreeMap<String, IVMType>, ErrorKind> {
+        match self.peek_mut()? {
+            IVMType::Map { value } => Ok(value),
+            other => Err(ErrorKind::TypeMismatch {
+                expected: "Map",
+                found: other.type_name(),
+            }),
+        }
+    }
+
+    fn pop_boolean(&mut self) -> Result<bool, ErrorKind> {
+        match self.pop()? {
+            IVMType::Boolean { value } => Ok(value),
+            other => Err(ErrorKind::TypeMismatch {
+                expected: "Boolean",
+                found: other.type_name(),
+            }),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&IVMType> {
+        self.frames
+            .last()
+            .and_then(|frame| frame.locals.get(name))
+            .or_else(|| self.memory.get(name))
+    }
+
+    /// Stores `value` in the current scope, or in the globals if `global` is
+    /// set, failing if that would create a variable past the limit.
+    fn store(&mut self, name: &str, value: IVMType, global: bool) -> Result<(), ErrorKind> {
+        let scope = match self.frames.last_mut() {
+            Some(frame) if !global => &mut frame.locals,
+            _ => &mut self.memory,
+        };
+
+        match scope.get_mut(name) {
+            Some(slot) => {
+                let old = std::mem::replace(slot, value);
+                self.release(self.usage_of(&old));
+            }
+            None => {
+                if let Some(limit) = self.limits.variables
+                    && self.variable_count >= limit
+                {
+                    return Err(ErrorKind::TooManyVariables(limit));
                 }
-                OP_STORE => {
-                    if !self.can_advance(1) {
-                        println!("Error: Incomplete STORE instruction");
-                        break;
-                    }
 
-                    let str_len = self.bytecode[self.index] as usize;
-                    self.index += 1;
+                scope.insert(name.to_string(), value);
+                self.variable_count += 1;
+            }
+        }
 
-                    if !self.can_advance(str_len) {
-                        println!("Error: Incomplete STORE key data");
-                        break;
-                    }
+        Ok(())
+    }
 
-                    let key_bytes = &self.bytecode[self.index..self.index + str_len];
-                    let key = String::from_utf8_lossy(key_bytes).to_string();
-                    self.index += str_len;
+    fn require(&self, allowed: bool, action: &'static str) -> Result<(), ErrorKind> {
+        if allowed {
+            Ok(())
+        } else {
+            Err(ErrorKind::PermissionDenied(action))
+        }
+    }
 
-                    if resolve {
-                        continue;
-                    }
+    fn check_stack_depth(&self) -> Result<(), ErrorKind> {
+        match self.limits.stack_depth {
+            Some(limit) if self.stack.len() > limit => Err(ErrorKind::StackOverflow(limit)),
+            _ => Ok(()),
+        }
+    }
 
-                    let value = match self.stack.pop() {
-                        Some(val) => val,
-                        None => {
-                            println!("Error: Stack underflow on STORE");
-                            break;
-                        }
-                    };
+    /// The usage of `value`, or nothing if no limit needs it counted.
+    fn usage_of(&self, value: &IVMType) -> Usage {
+        if self.limits.counts_usage() {
+            Usage::of(value)
+        } else {
+            Usage::default()
+        }
+    }
 
-                    self.memory.insert(key, value);
-                }
-                OP_STORE_REF => {
-                    if resolve {
-                        continue;
-                    }
+    /// Counts `usage` as held by the program, failing if that would go over a
+    /// limit. Called before the memory is allocated wherever its size is known
+    /// up front.
+    fn allocate(&mut self, usage: Usage) -> Result<(), ErrorKind> {
+        if !self.limits.counts_usage() {
+            return Ok(());
+        }
 
-                    let loc = match self.stack.pop() {
-                        Some(IVMType::String { value }) => value,
-                        _ => {
-                            println!("Error: Expected String on stack for STORE_REF");
-                            break;
-                        }
-                    };
+        let total = self.usage + usage;
+        if let Some(limit) = self.limits.string_bytes
+            && total.string_bytes > limit
+        {
+            return Err(ErrorKind::StringBytesExceeded(limit));
+        }
+        if let Some(limit) = self.limits.items
+            && total.items > limit
+        {
+            return Err(ErrorKind::CollectionItemsExceeded(limit));
+        }
 
-                    let value = match self.stack.pop() {
-                        Some(val) => val,
-                        None => {
-                            println!("Error: Stack underflow on STORE_REF");
-                            break;
-                        }
-                    };
+        self.usage = total;
+        Ok(())
+    }
 
-                    self.memory.insert(loc, value);
-                }
-                OP_DUP => {
-                    if resolve {
-                        continue;
-                    }
+    /// Accounts for a new string of `length` bytes. Without a configured
+    /// limit this still rejects lengths no allocation can hold.
+    fn allocate_string(&mut self, length: usize) -> Result<(), ErrorKind> {
+        let limit = isize::MAX as usize;
+        if length > limit {
+            return Err(ErrorKind::StringTooLong { length, limit });
+        }
 
-                    let value = match self.stack.last() {
-                        Some(val) => val.clone(),
-                        None => {
-                            println!("Error: Stack underflow on DUP");
-                            break;
-                        }
-                    };
+        self.allocate(Usage::string(length))
+    }
 
-                    self.stack.push(value);
-                }
-                OP_SWAP => {
-                    if resolve {
-                        continue;
-                    }
+    /// Accounts for a copy of `value` about to be pushed.
+    fn allocate_copy(&mut self, value: &IVMType) -> Result<(), ErrorKind> {
+        self.allocate(self.usage_of(value))
+    }
 
-                    if self.stack.len() < 2 {
-                        println!("Error: Stack underflow on SWAP");
-                        break;
-                    }
+    fn release(&mut self, usage: Usage) {
+        self.usage = self.usage - usage;
+    }
 
-                    let len = self.stack.len();
-                    self.stack.swap(len - 1, len - 2);
-                }
-                OP_POP => {
-                    if resolve {
-                        continue;
-                    }
+    /// Counts everything the program holds from scratch.
+    fn held_usage(&self) -> Usage {
+        let locals = self.frames.iter().flat_map(|frame| frame.locals.values());
+        self.stack
+            .iter()
+            .chain(self.memory.values())
+            .chain(locals)
+            .fold(Usage::default(), |usage, value| usage + Usage::of(value))
+    }
 
-                    if self.stack.is_empty() {
-                        println!("Error: Stack underflow on POP");
-                        break;
-                    }
+    fn jump_to(&mut self, target: &Target) -> Result<(), ErrorKind> {
+        match target.index {
+            Some(index) => {
+                self.pc = index + 1;
+                Ok(())
+            }
+            None => Err(ErrorKind::UndefinedLabel(target.label.clone())),
+        }
+    }
 
-                    self.stack.pop();
-                }
-                OP_FREE => {
-                    if !self.can_advance(1) {
-                        println!("Error: Incomplete FREE instruction");
-                        break;
-                    }
+    /// Runs the program until it halts, fails or uses up its step or time
+    /// budget. Execution continues from wherever the previous call stopped.
+    pub fn run(&mut self) -> Result<ExitStatus, VmError> {
+        let program = Rc::clone(&self.program);
+        let instructions = program.instructions();
+        let deadline = self
+            .timeout
+            .and_then(|timeout| Instant::now().checked_add(timeout));
+        let mut steps = 0u64;
+
+        while let Some((offset, instruction)) = instructions.get(self.pc) {
+            if self.max_steps.is_some_and(|max_steps| steps >= max_steps) {
+                return Ok(ExitStatus::BudgetExhausted);
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Ok(ExitStatus::BudgetExhausted);
+            }
+            steps += 1;
 
-                    let str_len = self.bytecode[self.index] as usize;
-                    self.index += 1;
+            if let Some(status) = self.step_instruction(*offset, instruction)? {
+                return Ok(status);
+            }
+        }
 
-                    if !self.can_advance(str_len) {
-                        println!("Error: Incomplete FREE key data");
-                        break;
-                    }
+        Ok(ExitStatus::Halted)
+    }
 
-                    let key_bytes = &self.bytecode[self.index..self.index + str_len];
-                    let key = String::from_utf8_lossy(key_bytes).to_string();
-                    self.index += str_len;
+    /// Executes the next instruction, ignoring the step and time budget.
+    /// Returns a status once the program has halted or exited.
+    pub fn step(&mut self) -> Result<Option<ExitStatus>, VmError> {
+        let program = Rc::clone(&self.program);
+        let Some((offset, instruction)) = program.instructions().get(self.pc) else {
+            return Ok(Some(ExitStatus::Halted));
+        };
+
+        let status = self.step_instruction(*offset, instruction)?;
+        if status.is_none() && self.pc >= program.instructions().len() {
+            return Ok(Some(ExitStatus::Halted));
+        }
 
-                    if resolve {
-                        continue;
-                    }
+        Ok(status)
+    }
 
-                    self.memory.remove(&key);
+    fn step_instruction(
+        &mut self,
+        offset: usize,
+        instruction: &Instruction,
+    ) -> Result<Option<ExitStatus>, VmError> {
+        let stack_depth = self.stack.len();
+        self.pc += 1;
+
+        self.execute(instruction)
+            .and_then(|status| {
+                self.check_stack_depth()?;
+                Ok(status)
+            })
+            .map_err(|kind| {
+                // An instruction that fails part way can drop values it
+                // popped without releasing them.
+                if self.limits.counts_usage() {
+                    self.usage = self.held_usage();
                 }
-                OP_ADD => {
-                    if resolve {
-                        continue;
-                    }
 
-                    let rhs = match self.stack.pop() {
-                        Some(value) => value,
-                        _ => {
-                            println!("Error: Expected Integer on stack for ADD");
-                            break;
-                        }
-                    };
-                    let lhs = match self.stack.pop() {
-                        Some(value) => value,
-                        _ => {
-                            println!("Error: Expected Integer on stack for ADD");
-                            break;
-                        }
-                    };
-
-                    let result = lhs.add(&rhs);
-                    match result {
-                        Some(val) => self.stack.push(val),
-                        None => {
-                            println!("Error: Incompatible types for ADD");
-                            break;
-                        }
-                    }
+                VmError {
+                    opcode: instruction.opcode(),
+                    offset,
+                    stack_depth,
+                    kind,
                 }
-                OP_SUB => {
-                    if resolve {
-                        continue;
-                    }
+            })
+    }
 
-                    let rhs = match self.stack.pop() {
-                        Some(value) => value,
-                        _ => {
-                            println!("Error: Expected Integer on stack for SUB");
-                            break;
-                        }
-                    };
-                    let lhs = match self.stack.pop() {
-                        Some(value) => value,
-                        _ => {
-                            println!("Error: Expected Integer on stack for SUB");
-                            break;
-                        }
-                    };
-
-                    let result = lhs.sub(&rhs);
-                    match result {
-                        Some(val) => self.stack.push(val),
-                        None => {
-                            println!("Error: Incompatible types for SUB");
-                            break;
-                        }
-                    }
+    /// Executes one instruction, returning a status if it ends the run.
+    fn execute(&mut self, instruction: &Instruction) -> Result<Option<ExitStatus>, ErrorKind> {
+        match instruction {
+            Instruction::Debug => {
+                self.require(self.permissions.stdout, "writing to stdout")?;
+                let debug = format!(
+                    "Stack: {:?}\nMemory: {:?}\nLabels: {:?}\nFrames: {:?}\n",
+                    self.stack,
+                    self.memory,
+                    self.program.labels(),
+                    self.frames
+                );
+                self.io
+                    .write_stdout(debug.as_bytes())
+                    .map_err(ErrorKind::Io)?;
+            }
+            Instruction::PushInteger { value } => {
+                self.stack.push(IVMType::Integer { value: *value })
+            }
+            Instruction::PushFloat { value } => self.stack.push(IVMType::Float { value: *value }),
+            Instruction::PushString { value } => {
+                self.allocate_string(value.len())?;
+                self.stack.push(IVMType::String {
+                    value: value.clone(),
+                });
+            }
+            Instruction::PushBoolean { value } => {
+                self.stack.push(IVMType::Boolean { value: *value })
+            }
+            Instruction::PushIntegerPower { power } => {
+                let value = 2i64
+                    .checked_pow(*power as u32)
+                    .ok_or(ErrorKind::IntegerOverflow)?;
+                self.stack.push(IVMType::Integer { value });
+            }
+            Instruction::PushIntegerPowerSub { power } => {
+                let value = 2u64
+                    .checked_pow(*power as u32)
+                    .and_then(|value| i64::try_from(value - 1).ok())
+                    .ok_or(ErrorKind::IntegerOverflow)?;
+                self.stack.push(IVMType::Integer { value });
+            }
+            Instruction::Load { name } => {
+                let value = self
+                    .lookup(name)
+                    .cloned()
+                    .ok_or_else(|| ErrorKind::UndefinedVariable(name.clone()))?;
+                self.allocate_copy(&value)?;
+                self.stack.push(value);
+            }
+            Instruction::LoadGlobal { name } => {
+                let value = self
+                    .memory
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| ErrorKind::UndefinedVariable(name.clone()))?;
+                self.allocate_copy(&value)?;
+                self.stack.push(value);
+            }
+            Instruction::PushNull => self.stack.push(IVMType::Null),
+            Instruction::PushBigInteger { value } => self.stack.push(IVMType::BigInteger {
+                value: value.clone(),
+            }),
+            Instruction::LoadOrNull { name } => {
+                let value = self.lookup(name).cloned().unwrap_or(IVMType::Null);
+                self.allocate_copy(&value)?;
+                self.stack.push(value);
+            }
+            Instruction::IsNull => {
+                let value = self.pop()?;
+                self.stack.push(IVMType::Boolean {
+                    value: value == IVMType::Null,
+                });
+            }
+            Instruction::LoadRef => {
+                let loc = self.pop_string()?;
+
+                let Some(value) = self.lookup(&loc).cloned() else {
+                    return Err(ErrorKind::UndefinedVariable(loc));
+                };
+                self.allocate_copy(&value)?;
+                self.stack.push(value);
+            }
+            Instruction::Store { name } => {
+                let value = self.pop_kept()?;
+                self.store(name, value, false)?;
+            }
+            Instruction::StoreGlobal { name } => {
+                let value = self.pop_kept()?;
+                self.store(name, value, true)?;
+            }
+            Instruction::StoreRef => {
+                let loc = self.pop_string()?;
+                let value = self.pop_kept()?;
+                self.store(&loc, value, false)?;
+            }
+            Instruction::Dup => {
+                if self.stack.len() <= self.stack_base() {
+                    return Err(ErrorKind::StackUnderflow);
                 }
-                OP_MUL => {
-                    if resolve {
-                        continue;
-                    }
 
-                    let rhs = match self.stack.pop() {
-                        Some(value) => value,
-                        _ => {
-                            println!("Error: Expected Integer on stack for MUL");
-                            break;
-                        }
-                    };
-                    let lhs = match self.stack.pop() {
-                        Some(value) => value,
-                        _ => {
-                            println!("Error: Expected Integer on stack for MUL");
-                            break;
-                        }
-                    };
-
-                    let result = lhs.mul(&rhs);
-                    match result {
-                        Some(val) => self.stack.push(val),
-                        None => {
-                            println!("Error: Incompatible types for MUL");
-                            break;
-                        }
-                    }
+                let value = self.stack.last().ok_or(ErrorKind::StackUnderflow)?.clone();
+                self.allocate_copy(&value)?;
+                self.stack.push(value);
+            }
+            Instruction::Swap => {
+                if self.stack.len() < self.stack_base() + 2 {
+                    return Err(ErrorKind::StackUnderflow);
                 }
-                OP_DIV => {
-                    if resolve {
-                        continue;
-                    }
 
-                    let rhs = match self.stack.pop() {
-                        Some(value) => value,
-                        _ => {
-                            println!("Error: Expected Integer on stack for DIV");
-                            break;
-                        }
-                    };
-                    let lhs = match self.stack.pop() {
-                        Some(value) => value,
-                        _ => {
-                            println!("Error: Expected Integer on stack for DIV");
-                            break;
-                        }
-                    };
-
-                    let result = lhs.div(&rhs);
-                    match result {
-                        Some(val) => self.stack.push(val),
-                        None => {
-                            println!("Error: Incompatible types for DIV or division by zero");
-                            break;
-                        }
-                    }
+                let len = self.stack.len();
+                self.stack.swap(len - 1, len - 2);
+            }
+            Instruction::Pop => {
+                self.pop()?;
+            }
+            Instruction::Free { name } => {
+                let local = self
+                    .frames
+                    .last_mut()
+                    .and_then(|frame| frame.locals.remove(name));
+
+                if let Some(value) = local.or_else(|| self.memory.remove(name)) {
+                    self.variable_count -= 1;
+                    self.release(self.usage_of(&value));
                 }
-                OP_MOD => {
-                    if resolve {
-                        continue;
-                    }
-
-                    let rhs = match self.stack.pop() {
-                        Some(value) => value,
-                        _ => {
-                            println!("Error: Expected Integer on stack for MOD");
-                            break;
-                        }
-                    };
-                    let lhs = match self.stack.pop() {
-                        Some(value) => value,
-                        _ => {
-                            println!("Error: Expected Integer on stack for MOD");
-                            break;
-                        }
-                    };
-
-                    let result = lhs.modulo(&rhs);
-                    match result {
-                        Some(val) => self.stack.push(val),
-                        None => {
-                            println!("Error: Incompatible types for MOD or modulo by zero");
-                            break;
-                        }
-                    }
+            }
+            Instruction::Add
+            | Instruction::Sub
+            | Instruction::Mul
+            | Instruction::Div
+            | Instruction::Mod
+            | Instruction::AddWrapping
+            | Instruction::SubWrapping
+            | Instruction::MulWrapping
+            | Instruction::AddSaturating
+            | Instruction::SubSaturating
+            | Instruction::MulSaturating => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
+
+                if let Some(length) = string_result_length(instruction, &lhs, &rhs) {
+                    self.allocate_string(length)?;
                 }
-                OP_STR_GET_SLICE => {
-                    if resolve {
-                        continue;
-                    }
 
-                    let end = match self.stack.pop() {
-                        Some(IVMType::Integer { value }) => value as usize,
-                        _ => {
-                            println!("Error: Expected Integer on stack for STR_GET_SLICE");
-                            break;
-                        }
-                    };
-
-                    let start = match self.stack.pop() {
-                        Some(IVMType::Integer { value }) => value as usize,
-                        _ => {
-                            println!("Error: Expected Integer on stack for STR_GET_SLICE");
-                            break;
-                        }
-                    };
-
-                    let val = match self.stack.pop() {
-                        Some(IVMType::String { value }) => value,
-                        _ => {
-                            println!("Error: Expected String on stack for STR_GET_SLICE");
-                            break;
-                        }
-                    };
+                let result = match instruction {
+                    Instruction::Add => lhs.add(&rhs)?,
+                    Instruction::Sub => lhs.sub(&rhs)?,
+                    Instruction::Mul => lhs.mul(&rhs)?,
+                    Instruction::Div => lhs.div(&rhs)?,
+                    Instruction::AddWrapping => lhs.integer_op(&rhs, i64::wrapping_add)?,
+                    Instruction::SubWrapping => lhs.integer_op(&rhs, i64::wrapping_sub)?,
+                    Instruction::MulWrapping => lhs.integer_op(&rhs, i64::wrapping_mul)?,
+                    Instruction::AddSaturating => lhs.integer_op(&rhs, i64::saturating_add)?,
+                    Instruction::SubSaturating => lhs.integer_op(&rhs, i64::saturating_sub)?,
+                    Instruction::MulSaturating => lhs.integer_op(&rhs, i64::saturating_mul)?,
+                    _ => lhs.modulo(&rhs)?,
+                };
+
+                self.stack.push(result);
+            }
+            Instruction::And | Instruction::Or | Instruction::Xor => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
 
-                    if start >= val.len() || end > val.len() || start >= end {
-                        println!("Error: Invalid slice indices for STR_GET_SLICE");
-                        break;
+                let result = match instruction {
+                    Instruction::And => {
+                        lhs.bitwise(&rhs, |lhs, rhs| lhs & rhs, |lhs, rhs| lhs && rhs)?
                     }
-
-                    let slice = &val[start..end];
-                    self.stack.push(IVMType::String {
-                        value: slice.to_string(),
-                    });
-                }
-                OP_STR_LENGTH => {
-                    if resolve {
-                        continue;
+                    Instruction::Or => {
+                        lhs.bitwise(&rhs, |lhs, rhs| lhs | rhs, |lhs, rhs| lhs || rhs)?
                     }
+                    _ => lhs.bitwise(&rhs, |lhs, rhs| lhs ^ rhs, |lhs, rhs| lhs != rhs)?,
+                };
 
-                    let val = match self.stack.pop() {
-                        Some(IVMType::String { value }) => value,
-                        _ => {
-                            println!("Error: Expected String on stack for STR_LENGTH");
-                            break;
-                        }
-                    };
+                self.stack.push(result);
+            }
+            Instruction::Not => {
+                let result = match self.pop()? {
+                    IVMType::Integer { value } => IVMType::Integer { value: !value },
+                    IVMType::Boolean { value } => IVMType::Boolean { value: !value },
+                    other => {
+                        return Err(ErrorKind::TypeMismatch {
+                            expected: "Integer or Boolean",
+                            found: other.type_name(),
+                        });
+                    }
+                };
+
+                self.stack.push(result);
+            }
+            Instruction::Shl | Instruction::Shr => {
+                let amount = self.pop_integer()?;
+                let value = self.pop_integer()?;
 
-                    let length = val.len() as i64;
-                    self.stack.push(IVMType::Integer { value: length });
+                if !(0..64).contains(&amount) {
+                    return Err(ErrorKind::InvalidShift(amount));
                 }
-                OP_CAST => {
-                    if !self.can_advance(1) {
-                        println!("Error: Incomplete CAST instruction");
-                        break;
-                    }
 
-                    let cast_type = self.bytecode[self.index];
-                    self.index += 1;
+                let value = match instruction {
+                    Instruction::Shl => value << amount,
+                    _ => value >> amount,
+                };
+                self.stack.push(IVMType::Integer { value });
+            }
+            Instruction::StrGetSlice => {
+                let end = self.pop_integer()?;
+                let start = self.pop_integer()?;
+                let val = self.pop_string()?;
+                let length = val.chars().count();
+
+                if start < 0 || start > end || end as usize > length {
+                    return Err(ErrorKind::InvalidSlice { start, end, length });
+                }
 
-                    if resolve {
-                        continue;
-                    }
+                let byte_index = |index: i64| {
+                    val.char_indices()
+                        .nth(index as usize)
+                        .map_or(val.len(), |(byte_index, _)| byte_index)
+                };
+                let slice = &val[byte_index(start)..byte_index(end)];
+                self.allocate_string(slice.len())?;
+
+                self.stack.push(IVMType::String {
+                    value: slice.to_string(),
+                });
+            }
+            Instruction::StrLength => {
+                let val = self.pop_string()?;
+                let length = val.chars().count() as i64;
+                self.stack.push(IVMType::Integer { value: length });
+            }
+            Instruction::StrFind => {
+                let needle = self.pop_string()?;
+                let val = self.pop_string()?;
+
+                let index = match val.find(&needle) {
+                    Some(byte_index) => val[..byte_index].chars().count() as i64,
+                    None => -1,
+                };
+                self.stack.push(IVMType::Integer { value: index });
+            }
+            Instruction::StrContains | Instruction::StrStartsWith | Instruction::StrEndsWith => {
+                let needle = self.pop_string()?;
+                let val = self.pop_string()?;
+
+                let value = match instruction {
+                    Instruction::StrContains => val.contains(&needle),
+                    Instruction::StrStartsWith => val.starts_with(&needle),
+                    _ => val.ends_with(&needle),
+                };
+                self.stack.push(IVMType::Boolean { value });
+            }
+            Instruction::StrReplace => {
+                let replacement = self.pop_string()?;
+                let pattern = self.pop_string()?;
+                let val = self.pop_string()?;
+
+                let count = val.matches(&pattern).count();
+                let length = (val.len() - count * pattern.len())
+                    .saturating_add(count.saturating_mul(replacement.len()));
+                self.allocate_string(length)?;
+
+                self.stack.push(IVMType::String {
+                    value: val.replace(&pattern, &replacement),
+                });
+            }
+            Instruction::StrSplit => {
+                let separator = self.pop_string()?;
+                let val = self.pop_string()?;
+
+                let count = match separator.as_str() {
+                    "" => val.chars().count(),
+                    _ => val.matches(&separator).count() + 1,
+                };
+                let length = val.len() - (count - 1) * separator.len();
+                self.allocate(Usage {
+                    string_bytes: length,
+                    items: count,
+                })?;
+
+                let parts = if separator.is_empty() {
+                    val.chars()
+                        .map(|c| IVMType::String {
+                            value: c.to_string(),
+                        })
+                        .collect()
+                } else {
+                    val.split(&separator)
+                        .map(|part| IVMType::String {
+                            value: part.to_string(),
+                        })
+                        .collect()
+                };
+                self.stack.push(IVMType::List { value: parts });
+            }
+            Instruction::StrJoin => {
+                let separator = self.pop_string()?;
+                let list = self.pop_list()?;
+
+                let parts = list
+                    .into_iter()
+                    .map(|item| match item {
+                        IVMType::String { value } => Ok(value),
+                        other => Err(ErrorKind::TypeMismatch {
+                            expected: "String",
+                            found: other.type_name(),
+                        }),
+                    })
+                    .collect::<Result<Vec<String>, ErrorKind>>()?;
+
+                let length = parts.iter().map(String::len).sum::<usize>()
+                    + separator.len() * parts.len().saturating_sub(1);
+                self.allocate_string(length)?;
+
+                self.stack.push(IVMType::String {
+                    value: parts.join(&separator),
+                });
+            }
+            Instruction::StrUpper | Instruction::StrLower | Instruction::StrTrim => {
+                let val = self.pop_string()?;
+
+                let length = match instruction {
+                    Instruction::StrUpper => val
+                        .chars()
+                        .flat_map(char::to_uppercase)
+                        .map(char::len_utf8)
+                        .sum(),
+                    Instruction::StrLower => val
+                        .chars()
+                        .flat_map(char::to_lowercase)
+                        .map(char::len_utf8)
+                        .sum(),
+                    _ => val.trim().len(),
+                };
+                self.allocate_string(length)?;
+
+                let value = match instruction {
+                    Instruction::StrUpper => val.to_uppercase(),
+                    Instruction::StrLower => val.to_lowercase(),
+                    _ => val.trim().to_string(),
+                };
+                self.stack.push(IVMType::String { value });
+            }
+            Instruction::StrRepeat => {
+                let count = self.pop_integer()?;
+                let val = self.pop_string()?;
 
-                    let value = match self.stack.pop() {
-                        Some(val) => val,
-                        None => {
-                            println!("Error: Stack underflow on CAST");
-                            break;
-                        }
-                    };
+                if count < 0 {
+                    return Err(ErrorKind::InvalidRepeatCount(count));
+                }
+                self.allocate_string(val.len().saturating_mul(count as usize))?;
 
-                    match (cast_type, value) {
-                        (CAST_TYPE_ITOS, IVMType::Integer { value }) => {
-                            let char_value = std::char::from_u32(value as u32);
-                            match char_value {
-                                Some(c) => self.stack.push(IVMType::String {
+                self.stack.push(IVMType::String {
+                    value: val.repeat(count as usize),
+                });
+            }
+            Instruction::Cast { cast_type } => {
+                let value = self.pop()?;
+
+                match (*cast_type, value) {
+                    (CAST_TYPE_ITOS, IVMType::Integer { value }) => {
+                        let char_value = u32::try_from(value).ok().and_then(char::from_u32);
+                        match char_value {
+                            Some(c) => {
+                                self.allocate_string(c.len_utf8())?;
+                                self.stack.push(IVMType::String {
                                     value: c.to_string(),
-                                }),
-                                None => {
-                                    println!("Error: Invalid integer value for ITOS cast");
-                                    break;
-                                }
+                                });
                             }
+                            None => return Err(ErrorKind::InvalidCodepoint(value)),
                         }
-                        (CAST_TYPE_STOI, IVMType::String { value }) => {
-                            let chars = value.chars().collect::<Vec<char>>();
-                            if chars.len() != 1 {
-                                println!("Error: Invalid string length for STOI cast");
-                                break;
-                            }
-
-                            self.stack.push(IVMType::Integer {
-                                value: chars[0] as i64,
-                            });
+                    }
+                    (CAST_TYPE_STOI, IVMType::String { value }) => {
+                        let chars = value.chars().collect::<Vec<char>>();
+                        if chars.len() != 1 {
+                            return Err(ErrorKind::InvalidCharacterCount(chars.len()));
                         }
-                        _ => {
-                            println!("Error: Invalid CAST operation");
-                            break;
+
+                        self.stack.push(IVMType::Integer {
+                            value: chars[0] as i64,
+                        });
+                    }
+                    (CAST_TYPE_ITOBIG, IVMType::Integer { value }) => {
+                        self.stack.push(IVMType::BigInteger {
+                            value: BigInt::from(value),
+                        });
+                    }
+                    (CAST_TYPE_BIGTOI, IVMType::BigInteger { value }) => {
+                        let value = value.to_i64().ok_or(ErrorKind::IntegerOverflow)?;
+                        self.stack.push(IVMType::Integer { value });
+                    }
+                    (CAST_TYPE_ITOF, IVMType::Integer { value }) => {
+                        self.stack.push(IVMType::Float {
+                            value: value as f64,
+                        });
+                    }
+                    (
+                        CAST_TYPE_FTOI | CAST_TYPE_FTOI_ROUND | CAST_TYPE_FTOI_FLOOR
+                        | CAST_TYPE_FTOI_CEIL,
+                        IVMType::Float { value },
+                    ) => {
+                        let rounded = match *cast_type {
+                            CAST_TYPE_FTOI => value.trunc(),
+                            CAST_TYPE_FTOI_ROUND => value.round(),
+                            CAST_TYPE_FTOI_FLOOR => value.floor(),
+                            _ => value.ceil(),
+                        };
+
+                        // i64::MIN is a power of two, so both bounds are exact
+                        // as floats. NaN fails both comparisons.
+                        if !(rounded >= i64::MIN as f64 && rounded < -(i64::MIN as f64)) {
+                            return Err(ErrorKind::FloatOutOfRange(value));
                         }
-                    }
-                }
-                OP_CMP => {
-                    if !self.can_advance(1) {
-                        println!("Error: Incomplete CMP instruction");
-                        break;
-                    }
 
-                    let cmp_type = self.bytecode[self.index];
-                    self.index += 1;
+                        self.stack.push(IVMType::Integer {
+                            value: rounded as i64,
+                        });
+                    }
+                    (CAST_TYPE_BOOLTOI, IVMType::Boolean { value }) => {
+                        self.stack.push(IVMType::Integer {
+                            value: value as i64,
+                        });
+                    }
+                    (CAST_TYPE_ITOBOOL, IVMType::Integer { value }) => {
+                        self.stack.push(IVMType::Boolean { value: value != 0 });
+                    }
+                    (
+                        CAST_TYPE_FORMAT,
+                        value @ (IVMType::Integer { .. }
+                        | IVMType::Float { .. }
+                        | IVMType::Boolean { .. }
+                        | IVMType::BigInteger { .. }),
+                    ) => {
+                        let value = value.to_string();
+                        self.allocate_string(value.len())?;
+                        self.stack.push(IVMType::String { value });
+                    }
+                    (CAST_TYPE_PARSE_INT, IVMType::String { value }) => {
+                        let Some(parsed) = parse_integer(value.trim()) else {
+                            return Err(ErrorKind::ParseFailure {
+                                text: value,
+                                expected: "Integer",
+                            });
+                        };
 
-                    if resolve {
-                        continue;
+                        self.stack.push(IVMType::Integer { value: parsed });
                     }
+                    (CAST_TYPE_PARSE_FLOAT, IVMType::String { value }) => {
+                        let Ok(parsed) = value.trim().parse::<f64>() else {
+                            return Err(ErrorKind::ParseFailure {
+                                text: value,
+                                expected: "Float",
+                            });
+                        };
 
-                    let rhs = match self.stack.pop() {
-                        Some(val) => val,
-                        None => {
-                            println!("Error: Stack underflow on CMP");
-                            break;
-                        }
-                    };
-                    let lhs = match self.stack.pop() {
-                        Some(val) => val,
-                        None => {
-                            println!("Error: Stack underflow on CMP");
-                            break;
-                        }
-                    };
-
-                    match lhs.compare(&rhs, cmp_type) {
-                        Some(result) => self.stack.push(IVMType::Boolean { value: result }),
-                        None => {
-                            println!("Error: Incompatible types for comparison");
-                            break;
-                        }
+                        self.stack.push(IVMType::Float { value: parsed });
                     }
-                }
-                OP_LABEL => {
-                    if !self.can_advance(1) {
-                        println!("Error: Incomplete LABEL instruction");
-                        break;
+                    (_, value) => {
+                        return Err(ErrorKind::InvalidCast {
+                            cast_type: *cast_type,
+                            found: value.type_name(),
+                        });
                     }
+                }
+            }
+            Instruction::ListNew => self.stack.push(IVMType::List { value: Vec::new() }),
+            Instruction::ListPush => {
+                let item = self.pop_kept()?;
+                self.allocate(Usage::items(1))?;
 
-                    let str_len = self.bytecode[self.index] as usize;
-                    self.index += 1;
+                self.peek_list_mut()?.push(item);
+            }
+            Instruction::ListPop => {
+                let item = self.peek_list_mut()?.pop().ok_or(ErrorKind::EmptyList)?;
+                self.release(Usage::items(1));
 
-                    if !self.can_advance(str_len) {
-                        println!("Error: Incomplete LABEL name data");
-                        break;
-                    }
+                self.stack.push(item);
+            }
+            Instruction::ListGet => {
+                let index = self.pop_integer()?;
+                let list = self.peek_list()?;
+                let position = list_index(index, list.len())?;
+
+                let item = list[position].clone();
+                self.allocate_copy(&item)?;
+                self.stack.push(item);
+            }
+            Instruction::ListSet => {
+                let item = self.pop_kept()?;
+                let index = self.pop_integer()?;
+                let list = self.peek_list_mut()?;
+                let position = list_index(index, list.len())?;
+
+                let old = std::mem::replace(&mut list[position], item);
+                self.release(self.usage_of(&old));
+            }
+            Instruction::ListLength => {
+                let length = self.peek_list()?.len() as i64;
+                self.stack.push(IVMType::Integer { value: length });
+            }
+            Instruction::ListSlice => {
+                let end = self.pop_integer()?;
+                let start = self.pop_integer()?;
+                let list = self.peek_list()?;
+
+                if start < 0 || end < start || end as usize > list.len() {
+                    return Err(ErrorKind::InvalidSlice {
+                        start,
+                        end,
+                        length: list.len(),
+                    });
+                }
 
-                    let label_bytes = &self.bytecode[self.index..self.index + str_len];
-                    let label_name = String::from_utf8_lossy(label_bytes).to_string();
-                    self.index += str_len;
+                let slice = IVMType::List {
+                    value: list[start as usize..end as usize].to_vec(),
+                };
+                self.allocate_copy(&slice)?;
+                self.stack.push(slice);
+            }
+            Instruction::MapNew => self.stack.push(IVMType::Map {
+                value: BTreeMap::new(),
+            }),
+            Instruction::MapGet => {
+                let key = self.pop_string()?;
+
+                let Some(item) = self.peek_map()?.get(&key).cloned() else {
+                    return Err(ErrorKind::KeyNotFound(key));
+                };
+                self.allocate_copy(&item)?;
+                self.stack.push(item);
+            }
+            Instruction::MapSet => {
+                let item = self.pop_kept()?;
+                let key = self.pop_string()?;
+
+                if !self.peek_map()?.contains_key(&key) {
+                    self.allocate(Usage {
+                        string_bytes: key.len(),
+                        items: 1,
+                    })?;
+                }
+                if let Some(old) = self.peek_map_mut()?.insert(key, item) {
+                    self.release(self.usage_of(&old));
+                }
+            }
+            Instruction::MapDelete => {
+                let key = self.pop_string()?;
 
-                    self.labels.insert(label_name, self.index);
+                if let Some((key, item)) = self.peek_map_mut()?.remove_entry(&key) {
+                    self.release(Usage::string(key.len()) + Usage::items(1));
+                    self.release(self.usage_of(&item));
                 }
-                OP_JUMP => {
-                    if !self.can_advance(1) {
-                        println!("Error: Incomplete JUMP instruction");
-                        break;
+            }
+            Instruction::MapContains => {
+                let key = self.pop_string()?;
+                let value = self.peek_map()?.contains_key(&key);
+                self.stack.push(IVMType::Boolean { value });
+            }
+            Instruction::MapKeys => {
+                let keys = self
+                    .peek_map()?
+                    .keys()
+                    .map(|key| IVMType::String { value: key.clone() })
+                    .collect();
+                let keys = IVMType::List { value: keys };
+                self.allocate_copy(&keys)?;
+
+                self.stack.push(keys);
+            }
+            Instruction::Math { math_type } => {
+                let value = match *math_type {
+                    MATH_TYPE_POW | MATH_TYPE_ATAN2 | MATH_TYPE_MIN | MATH_TYPE_MAX => {
+                        let rhs = self.pop_float()?;
+                        let lhs = self.pop_float()?;
+
+                        match *math_type {
+                            MATH_TYPE_POW => lhs.powf(rhs),
+                            MATH_TYPE_ATAN2 => lhs.atan2(rhs),
+                            MATH_TYPE_MIN => lhs.min(rhs),
+                            _ => lhs.max(rhs),
+                        }
                     }
+                    _ => {
+                        let value = self.pop_float()?;
+
+                        match *math_type {
+                            MATH_TYPE_SQRT => value.sqrt(),
+                            MATH_TYPE_ABS => value.abs(),
+                            MATH_TYPE_FLOOR => value.floor(),
+                            MATH_TYPE_CEIL => value.ceil(),
+                            MATH_TYPE_ROUND => value.round(),
+                            MATH_TYPE_SIN => value.sin(),
+                            MATH_TYPE_COS => value.cos(),
+                            MATH_TYPE_TAN => value.tan(),
+                            MATH_TYPE_EXP => value.exp(),
+                            MATH_TYPE_LN => value.ln(),
+                            _ => return Err(ErrorKind::InvalidMathType(*math_type)),
+                        }
+                    }
+                };
+
+                self.stack.push(IVMType::Float { value });
+            }
+            Instruction::Cmp { cmp_type } => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
 
-                    let str_len = self.bytecode[self.index] as usize;
-                    self.index += 1;
+                let result = lhs.compare(&rhs, *cmp_type)?;
+                self.stack.push(IVMType::Boolean { value: result });
+            }
+            Instruction::Jump { target } => self.jump_to(target)?,
+            Instruction::JumpIfTrue { target } => {
+                if self.pop_boolean()? {
+                    self.jump_to(target)?;
+                }
+            }
+            Instruction::JumpIfFalse { target } => {
+                if !self.pop_boolean()? {
+                    self.jump_to(target)?;
+                }
+            }
+            Instruction::Call { target } => {
+                let return_address = self.pc;
+                let (stack_base, returns) = match target
+                    .index
+                    .map(|index| &self.program.instructions()[index].1)
+                {
+                    Some(Instruction::Func { args, returns, .. }) => {
+                        let available = self.stack.len() - self.stack_base();
+                        if available < *args as usize {
+                            return Err(ErrorKind::ArgumentCount {
+                                function: target.label.clone(),
+                                expected: *args,
+                                found: available,
+                            });
+                        }
 
-                    if !self.can_advance(str_len) {
-                        println!("Error: Incomplete JUMP label data");
-                        break;
+                        (self.stack.len() - *args as usize, Some(*returns))
                     }
+                    _ => (self.stack_base(), None),
+                };
 
-                    let label_bytes = &self.bytecode[self.index..self.index + str_len];
-                    let label_name = String::from_utf8_lossy(label_bytes).to_string();
-                    self.index += str_len;
+                if let Some(limit) = self.limits.call_depth
+                    && self.frames.len() >= limit
+                {
+                    return Err(ErrorKind::CallDepthExceeded(limit));
+                }
 
-                    if resolve {
-                        continue;
-                    }
+                self.jump_to(target)?;
+                self.frames.push(Frame {
+                    return_address,
+                    locals: HashMap::new(),
+                    stack_base,
+                    returns,
+                });
+            }
+            Instruction::Return => {
+                let frame = self.frames.last().ok_or(ErrorKind::CallStackUnderflow)?;
 
-                    match self.labels.get(&label_name) {
-                        Some(&target_index) => self.index = target_index,
-                        None => {
-                            println!("Error: Label '{}' not found for JUMP", label_name);
-                            break;
-                        }
+                if let Some(returns) = frame.returns {
+                    let found = self.stack.len() - frame.stack_base;
+                    if found != returns as usize {
+                        return Err(ErrorKind::ReturnCount {
+                            expected: returns,
+                            found,
+                        });
                     }
                 }
-                OP_JUMP_IF_TRUE => {
-                    if !self.can_advance(1) {
-                        println!("Error: Incomplete JUMP_IF_TRUE instruction");
-                        break;
-                    }
 
-                    let str_len = self.bytecode[self.index] as usize;
-                    self.index += 1;
+                let frame = self.frames.pop().ok_or(ErrorKind::CallStackUnderflow)?;
+                self.variable_count -= frame.locals.len();
+                for value in frame.locals.values() {
+                    self.release(self.usage_of(value));
+                }
+                self.pc = frame.return_address;
+            }
+            Instruction::DisplayStdout => {
+                self.require(self.permissions.stdout, "writing to stdout")?;
+                let value = self.pop()?;
+                self.io
+                    .write_stdout(value.to_string().as_bytes())
+                    .map_err(ErrorKind::Io)?;
+            }
+            Instruction::DisplayStderr => {
+                self.require(self.permissions.stderr, "writing to stderr")?;
+                let value = self.pop()?;
+                self.io
+                    .write_stderr(value.to_string().as_bytes())
+                    .map_err(ErrorKind::Io)?;
+            }
+            Instruction::Input => {
+                self.require(self.permissions.stdin, "reading from stdin")?;
+                let input = self.io.read_line().map_err(ErrorKind::Io)?;
+                let input = input.trim_end().to_string();
+                self.allocate_string(input.len())?;
+                self.stack.push(IVMType::String { value: input });
+            }
+            Instruction::Exit => {
+                let code = self.pop_integer()?;
+                let code = i32::try_from(code).map_err(|_| ErrorKind::IntegerOverflow)?;
+                self.io.flush().map_err(ErrorKind::Io)?;
 
-                    if !self.can_advance(str_len) {
-                        println!("Error: Incomplete JUMP_IF_TRUE label data");
-                        break;
-                    }
+                self.pc = self.program.instructions().len();
+                return Ok(Some(ExitStatus::Exited(code)));
+            }
+            Instruction::Label { .. } | Instruction::Func { .. } => {}
+        }
 
-                    let label_bytes = &self.bytecode[self.index..self.index + str_len];
-                    let label_name = String::from_utf8_lossy(label_bytes).to_string();
-                    self.index += str_len;
+        Ok(None)
+    }
+}
 
-                    if resolve {
-                        continue;
-                    }
+/// The length of the string `ADD` or `MUL` would build from these operands,
+/// so it can be checked before anything is allocated.
+fn string_result_length(instruction: &Instruction, lhs: &IVMType, rhs: &IVMType) -> Option<usize> {
+    match (instruction, lhs, rhs) {
+        (Instruction::Add, IVMType::String { value: lhs }, IVMType::String { value: rhs }) => {
+            Some(lhs.len().saturating_add(rhs.len()))
+        }
+        (Instruction::Mul, IVMType::String { value }, IVMType::Integer { value: count }) => {
+            let count = usize::try_from(*count).unwrap_or(0);
+            Some(value.len().saturating_mul(count))
+        }
+        _ => None,
+    }
+}
 
-                    let condition = match self.stack.pop() {
-                        Some(IVMType::Boolean { value }) => value,
-                        _ => {
-                            println!("Error: Expected Boolean on stack for JUMP_IF_TRUE");
-                            break;
-                        }
-                    };
-
-                    if condition {
-                        match self.labels.get(&label_name) {
-                            Some(&target_index) => self.index = target_index,
-                            None => {
-                                println!(
-                                    "Error: Label '{}' not found for JUMP_IF_TRUE",
-                                    label_name
-                                );
-                                break;
-                            }
-                        }
-                    }
-                }
-                OP_JUMP_IF_FALSE => {
-                    if !self.can_advance(1) {
-                        println!("Error: Incomplete JUMP_IF_FALSE instruction");
-                        break;
-                    }
+fn list_index(index: i64, length: usize) -> Result<usize, ErrorKind> {
+    match usize::try_from(index) {
+        Ok(position) if position < length => Ok(position),
+        _ => Err(ErrorKind::IndexOutOfBounds { index, length }),
+    }
+}
 
-                    let str_len = self.bytecode[self.index] as usize;
-                    self.index += 1;
+#[cfg(test)]
+mod tests {
+    use std::io;
 
-                    if !self.can_advance(str_len) {
-                        println!("Error: Incomplete JUMP_IF_FALSE label data");
-                        break;
-                    }
+    use super::*;
+    use crate::{asm::assemble, host::BufferIo, instruction::DecodeError};
 
-                    let label_bytes = &self.bytecode[self.index..self.index + str_len];
-                    let label_name = String::from_utf8_lossy(label_bytes).to_string();
-                    self.index += str_len;
+    fn build(source: &str, configure: impl FnOnce(VMBuilder) -> VMBuilder) -> VM {
+        let program = VM::load(&assemble(source).unwrap()).unwrap();
+        configure(VM::builder(program).io(BufferIo::default())).build()
+    }
 
-                    if resolve {
-                        continue;
-                    }
+    fn error(source: &str, configure: impl FnOnce(VMBuilder) -> VMBuilder) -> ErrorKind {
+        build(source, configure).run().unwrap_err().kind
+    }
 
-                    let condition = match self.stack.pop() {
-                        Some(IVMType::Boolean { value }) => value,
-                        _ => {
-                            println!("Error: Expected Boolean on stack for JUMP_IF_FALSE");
-                            break;
-                        }
-                    };
-
-                    if !condition {
-                        match self.labels.get(&label_name) {
-                            Some(&target_index) => self.index = target_index,
-                            None => {
-                                println!(
-                                    "Error: Label '{}' not found for JUMP_IF_FALSE",
-                                    label_name
-                                );
-                                break;
-                            }
-                        }
-                    }
+    fn integers(values: &[i64]) -> IVMType {
+        IVMType::List {
+            value: values
+                .iter()
+                .map(|value| IVMType::Integer { value: *value })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn function_argument_and_return_counts_are_checked() {
+        let function = "JMP end\nFUNC add 2 1\nADD\nRET\nLABEL end";
+
+        let mut vm = build(
+            &format!("PUSH INT 1\nPUSH INT 2\nCALL add\n{}", function),
+            |vm| vm,
+        );
+        vm.run().unwrap();
+        assert_eq!(vm.stack(), [IVMType::Integer { value: 3 }]);
+
+        let kind = error(&format!("PUSH INT 1\nCALL add\n{}", function), |vm| vm);
+        assert!(
+            matches!(
+                &kind,
+                ErrorKind::ArgumentCount { function, expected: 2, found: 1 } if function == "add"
+            ),
+            "{:?}",
+            kind
+        );
+
+        let source = "PUSH INT 1\nPUSH INT 2\nCALL add\nJMP end\nFUNC add 2 1\nRET\nLABEL end";
+        let kind = error(source, |vm| vm);
+        assert!(
+            matches!(
+                kind,
+                ErrorKind::ReturnCount {
+                    expected: 1,
+                    found: 2
                 }
-                OP_CALL => {
-                    if !self.can_advance(1) {
-                        println!("Error: Incomplete CALL instruction");
-                        break;
-                    }
+            ),
+            "{:?}",
+            kind
+        );
+    }
 
-                    let str_len = self.bytecode[self.index] as usize;
-                    self.index += 1;
+    #[test]
+    fn list_reads_leave_the_list() {
+        let source = "LIST_NEW
+            PUSH INT 1
+            LIST_PUSH
+            PUSH INT 2
+            LIST_PUSH
+            PUSH INT 1
+            LIST_GET
+            POP
+            LIST_LENGTH
+            POP
+            PUSH INT 0
+            PUSH INT 1
+            LIST_SLICE";
+
+        let mut vm = build(source, |vm| vm);
+        vm.run().unwrap();
+        assert_eq!(vm.stack(), [integers(&[1, 2]), integers(&[1])]);
+    }
 
-                    if !self.can_advance(str_len) {
-                        println!("Error: Incomplete CALL label data");
-                        break;
-                    }
+    #[test]
+    fn big_integers_are_capped() {
+        let source = "PUSH BIGINT 18446744073709551617\nLABEL loop\nDUP\nMUL\nJMP loop";
+        let kind = error(source, |vm| vm);
+        assert!(matches!(kind, ErrorKind::BigIntegerTooLarge), "{:?}", kind);
+
+        let mut literal = vec![0xFF; (MAX_BITS / 8) as usize];
+        literal.push(0x01);
+        let mut bytecode = vec![OP_PUSH, PUSH_TYPE_BIG_INTEGER];
+        bytecode.extend((literal.len() as u32).to_le_bytes());
+        bytecode.extend(literal);
+
+        let err = VM::load(&bytecode).err().unwrap();
+        assert!(
+            matches!(
+                err,
+                LoadError::Decode(DecodeError {
+                    kind: ErrorKind::BigIntegerTooLarge,
+                    ..
+                })
+            ),
+            "{:?}",
+            err
+        );
+    }
 
-                    let label_bytes = &self.bytecode[self.index..self.index + str_len];
-                    let label_name = String::from_utf8_lossy(label_bytes).to_string();
-                    self.index += str_len;
+    #[test]
+    fn budget_exhausted_pauses_and_resumes() {
+        let source = "PUSH INT 0
+            STORE i
+            LABEL loop
+            LOAD i
+            PUSH INT 1
+            ADD
+            DUP
+            STORE i
+            PUSH INT 10
+            CMP LESS_THAN
+            JMP_IF_TRUE loop";
+
+        let mut vm = build(source, |vm| vm.max_steps(5));
+        let mut runs = 1;
+        while vm.run().unwrap() == ExitStatus::BudgetExhausted {
+            runs += 1;
+        }
 
-                    if resolve {
-                        continue;
-                    }
+        assert_eq!(runs, 17);
+        assert_eq!(vm.variable("i"), Some(&IVMType::Integer { value: 10 }));
+    }
 
-                    match self.labels.get(&label_name) {
-                        Some(&target_index) => {
-                            self.calls.push(self.index);
-                            self.index = target_index;
-                        },
-                        None => {
-                            println!("Error: Label '{}' not found for CALL", label_name);
-                            break;
-                        }
-                    }
-                }
-                OP_RETURN => {
-                    if resolve {
-                        continue;
-                    }
+    /// Takes longer to read a line than the timeout in
+    /// `timeout_is_checked_before_each_instruction`.
+    struct SlowInput;
 
-                    let return_address = match self.calls.pop() {
-                        Some(value) => value,
-                        _ => {
-                            println!("Error: Call stack underflow on RETURN");
-                            break;
-                        }
-                    };
+    impl IoHost for SlowInput {
+        fn read_line(&mut self) -> io::Result<String> {
+            std::thread::sleep(Duration::from_millis(20));
+            Ok(String::new())
+        }
 
-                    self.index = return_address;
-                }
-                OP_DISPLAY_STDOUT => {
-                    if resolve {
-                        continue;
-                    }
+        fn write_stdout(&mut self, _: &[u8]) -> io::Result<()> {
+            Ok(())
+        }
 
-                    let value = match self.stack.pop() {
-                        Some(val) => val,
-                        None => {
-                            println!("Error: Stack underflow on DISPLAY_STDOUT");
-                            break;
-                        }
-                    };
+        fn write_stderr(&mut self, _: &[u8]) -> io::Result<()> {
+            Ok(())
+        }
+    }
 
-                    match value {
-                        IVMType::Integer { value } => stdout.lock().write_all(format!("{}", value).as_bytes()).unwrap(),
-                        IVMType::Float { value } => stdout.lock().write_all(format!("{}", value).as_bytes()).unwrap(),
-                        IVMType::String { value } => stdout.lock().write_all(value.as_bytes()).unwrap(),
-                        IVMType::Boolean { value } => stdout.lock().write_all(format!("{}", value).as_bytes()).unwrap(),
-                    }
-                }
-                OP_DISPLAY_STDERR => {
-                    if resolve {
-                        continue;
-                    }
+    #[test]
+    fn timeout_is_checked_before_each_instruction() {
+        let mut vm = build("INPUT\nINPUT\nINPUT", |vm| {
+            vm.io(SlowInput).timeout(Duration::from_millis(10))
+        });
 
-                    let value = match self.stack.pop() {
-                        Some(val) => val,
-                        None => {
-                            println!("Error: Stack underflow on DISPLAY_STDERR");
-                            break;
-                        }
-                    };
+        for depth in 1..=2 {
+            assert_eq!(vm.run().unwrap(), ExitStatus::BudgetExhausted);
+            assert_eq!(vm.stack().len(), depth);
+        }
+        assert_eq!(vm.run().unwrap(), ExitStatus::Halted);
+        assert_eq!(vm.stack().len(), 3);
+    }
 
-                    match value {
-                        IVMType::Integer { value } => stderr.lock().write_all(format!("{}", value).as_bytes()).unwrap(),
-                        IVMType::Float { value } => stderr.lock().write_all(format!("{}", value).as_bytes()).unwrap(),
-                        IVMType::String { value } => stderr.lock().write_all(value.as_bytes()).unwrap(),
-                        IVMType::Boolean { value } => stderr.lock().write_all(format!("{}", value).as_bytes()).unwrap(),
-                    }
-                }
-                OP_INPUT => {
-                    if resolve {
-                        continue;
-                    }
+    #[test]
+    fn each_limit_fails_with_its_error() {
+        let kind = error("PUSH INT 1\nPUSH INT 2\nPUSH INT 3", |vm| {
+            vm.max_stack_depth(2)
+        });
+        assert!(matches!(kind, ErrorKind::StackOverflow(2)), "{:?}", kind);
+
+        let kind = error("LABEL f\nCALL f", |vm| vm.max_call_depth(2));
+        assert!(
+            matches!(kind, ErrorKind::CallDepthExceeded(2)),
+            "{:?}",
+            kind
+        );
+
+        // Storing to an existing variable does not count as a new one.
+        let source = "PUSH INT 1
+            STORE a
+            PUSH INT 2
+            STORE b
+            PUSH INT 3
+            STORE a
+            PUSH INT 4
+            STORE c";
+        let kind = error(source, |vm| vm.max_variables(2));
+        assert!(matches!(kind, ErrorKind::TooManyVariables(2)), "{:?}", kind);
+
+        let kind = error("PUSH STRING \"ab\"\nDUP\nDUP", |vm| vm.max_string_bytes(4));
+        assert!(
+            matches!(kind, ErrorKind::StringBytesExceeded(4)),
+            "{:?}",
+            kind
+        );
+
+        // Each DUP copies the list and LIST_PUSH nests the copy, doubling the
+        // number of items.
+        let source = "LIST_NEW\nPUSH INT 1\nLIST_PUSH\nLABEL l\nDUP\nLIST_PUSH\nJMP l";
+        let kind = error(source, |vm| vm.max_collection_items(16));
+        assert!(
+            matches!(kind, ErrorKind::CollectionItemsExceeded(16)),
+            "{:?}",
+            kind
+        );
+    }
 
-                    let mut input = String::new();
-                    match stdin.read_line(&mut input) {
-                        Ok(_) => {
-                            let input = input.trim_end().to_string();
-                            self.stack.push(IVMType::String { value: input });
-                        }
-                        Err(e) => {
-                            println!("Error reading input: {}", e);
-                            break;
-                        }
-                    }
-                }
-                OP_EXIT => {
-                    if resolve {
-                        continue;
-                    }
+    #[test]
+    fn dropped_values_are_released() {
+        let source = "PUSH STRING \"ab\"
+            STORE held
+            PUSH INT 0
+            STORE i
+            LABEL loop
+            PUSH STRING \"ab\"
+            POP
+            LIST_NEW
+            PUSH INT 1
+            LIST_PUSH
+            STORE list
+            LOAD i
+            PUSH INT 1
+            ADD
+            DUP
+            STORE i
+            PUSH INT 100
+            CMP LESS_THAN
+            JMP_IF_TRUE loop";
+
+        let mut vm = build(source, |vm| vm.max_string_bytes(4).max_collection_items(2));
+        assert_eq!(vm.run().unwrap(), ExitStatus::Halted);
+    }
 
-                    match self.stack.pop() {
-                        Some(IVMType::Integer { value: code }) => {
-                            std::process::exit(code as i32);
-                        }
-                        _ => {
-                            println!("Error: Expected Integer on stack for EXIT");
-                            break;
-                        }
-                    }
-                }
-                _ => {
-                    println!("Unknown opcode: 0x{:02X}", opcode);
-                    break;
-                }
+    #[test]
+    fn usage_matches_what_is_held() {
+        let source = "PUSH STRING \"key\"
+            STORE name
+            LIST_NEW
+            PUSH STRING \"a\"
+            LIST_PUSH
+            PUSH STRING \"bc\"
+            LIST_PUSH
+            DUP
+            PUSH INT 0
+            PUSH STRING \"xyz\"
+            LIST_SET
+            PUSH INT 0
+            LIST_GET
+            POP
+            LIST_POP
+            POP
+            PUSH INT 0
+            PUSH INT 1
+            LIST_SLICE
+            STORE slice
+            PUSH STRING \",\"
+            STR_JOIN
+            PUSH STRING \",\"
+            STR_SPLIT
+            STORE parts
+            LOAD parts
+            IS_NULL
+            POP
+            MAP_NEW
+            PUSH STRING \"k\"
+            PUSH STRING \"v\"
+            MAP_SET
+            PUSH STRING \"k\"
+            PUSH STRING \"w\"
+            MAP_SET
+            PUSH STRING \"k\"
+            MAP_GET
+            POP
+            MAP_KEYS
+            POP
+            PUSH STRING \"k\"
+            MAP_DELETE
+            STORE map
+            PUSH STRING \"x\"
+            PUSH INT 3
+            STR_REPEAT
+            STR_UPPER
+            PUSH STRING \"y\"
+            ADD
+            STORE name
+            LOAD name
+            PUSH STRING \"XXXy\"
+            CMP EQUAL
+            POP
+            PUSH INT 5
+            CAST FORMAT
+            PUSH STRING \"ref\"
+            STORE_REF
+            PUSH STRING \"ref\"
+            LOAD_REF
+            DISPLAY_STDOUT
+            FREE slice
+            PUSH STRING \"arg\"
+            CALL f
+            POP
+            JMP end
+            FUNC f 1 1
+            STORE local
+            LOAD local
+            PUSH STRING \"!\"
+            ADD
+            RET
+            LABEL end";
+
+        let mut vm = build(source, |vm| {
+            vm.max_steps(1)
+                .max_string_bytes(1024)
+                .max_collection_items(1024)
+        });
+        while vm.run().unwrap() == ExitStatus::BudgetExhausted {
+            assert_eq!(vm.usage, vm.held_usage());
+        }
+
+        assert_eq!(
+            vm.usage,
+            Usage {
+                string_bytes: 11,
+                items: 3
             }
+        );
+        assert_eq!(vm.usage, vm.held_usage());
+    }
+
+    #[test]
+    fn io_needs_its_permission() {
+        let cases = [
+            ("INPUT", "reading from stdin"),
+            ("PUSH INT 1\nDISPLAY_STDOUT", "writing to stdout"),
+            ("DEBUG", "writing to stdout"),
+            ("PUSH INT 1\nDISPLAY_STDERR", "writing to stderr"),
+        ];
+
+        for (source, denied) in cases {
+            let mut vm = build(source, |vm| vm.permissions(Permissions::ALL));
+            assert_eq!(vm.run().unwrap(), ExitStatus::Halted, "{}", source);
+
+            let kind = error(source, |vm| vm.permissions(Permissions::NONE));
+            assert!(
+                matches!(kind, ErrorKind::PermissionDenied(action) if action == denied),
+                "{}: {:?}",
+                source,
+                kind
+            );
         }
     }
+
+    #[test]
+    fn exit_returns_to_the_host() {
+        let mut vm = build("PUSH INT 3\nEXIT\nPUSH INT 4", |vm| vm);
+        assert_eq!(vm.run().unwrap(), ExitStatus::Exited(3));
+        assert_eq!(vm.run().unwrap(), ExitStatus::Halted);
+        assert!(vm.stack().is_empty());
+
+        let kind = error("PUSH INT 4294967296\nEXIT", |vm| vm);
+        assert!(matches!(kind, ErrorKind::IntegerOverflow), "{:?}", kind);
+    }
 }